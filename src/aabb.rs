@@ -48,6 +48,22 @@ impl Aabb {
             z: Interval::new_interval(&box0.z, &box1.z),
         }
     }
+    // 不相交时返回空盒, 而不是 min > max 的倒置区间
+    pub fn intersect(&self, other: &Aabb) -> Self {
+        let overlap = Aabb {
+            x: Interval::new(self.x.min.max(other.x.min), self.x.max.min(other.x.max)),
+            y: Interval::new(self.y.min.max(other.y.min), self.y.max.min(other.y.max)),
+            z: Interval::new(self.z.min.max(other.z.min), self.z.max.min(other.z.max)),
+        };
+        if overlap.is_empty() {
+            *AABB_EMPTY
+        } else {
+            overlap
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.x.min > self.x.max || self.y.min > self.y.max || self.z.min > self.z.max
+    }
     pub fn axis_interval(&self, n: u32) -> &Interval {
         if n == 1 {
             &self.y
@@ -58,6 +74,9 @@ impl Aabb {
         }
     }
     pub fn hit(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.hit_interval(r, ray_t).is_some()
+    }
+    pub fn hit_interval(&self, r: &Ray, ray_t: &Interval) -> Option<Interval> {
         let ray_orig = r.origin;
         let ray_dir = r.direction;
         let mut tmp_ray = *ray_t;
@@ -82,10 +101,35 @@ impl Aabb {
                 }
             }
             if tmp_ray.max <= tmp_ray.min {
-                return false;
+                return None;
             }
         }
-        true
+        Some(tmp_ray)
+    }
+    pub fn hit_inv(&self, origin: &Vec3, inv_dir: &Vec3, ray_t: &Interval) -> bool {
+        let mut t_min = ray_t.min;
+        let mut t_max = ray_t.max;
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let t0 = (ax.min - origin.axis(axis)) * inv_dir.axis(axis);
+            let t1 = (ax.max - origin.axis(axis)) * inv_dir.axis(axis);
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        t_min < t_max
+    }
+    pub fn surface_area(&self) -> f64 {
+        let dx = self.x.size().max(0.0);
+        let dy = self.y.size().max(0.0);
+        let dz = self.z.size().max(0.0);
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+    pub fn centroid(&self) -> Vec3 {
+        Vec3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
     }
     pub fn longest_axis(&self) -> usize {
         if self.x.size() > self.y.size() {
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum LoadError {
+    MissingFile(String),
+    Parse { path: PathBuf, message: String },
+    BadMtl { path: PathBuf, message: String },
+    MissingTexture(String),
+    MaterialOutOfRange { material: usize, count: usize },
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::MissingFile(filename) => write!(f, "could not find file \"{}\"", filename),
+            LoadError::Parse { path, message } => {
+                write!(f, "failed to parse {}: {}", path.display(), message)
+            }
+            LoadError::BadMtl { path, message } => {
+                write!(
+                    f,
+                    "failed to load materials of {}: {}",
+                    path.display(),
+                    message
+                )
+            }
+            LoadError::MissingTexture(filename) => {
+                write!(f, "could not load texture \"{}\"", filename)
+            }
+            LoadError::MaterialOutOfRange { material, count } => write!(
+                f,
+                "material index {} is out of range ({} materials)",
+                material, count
+            ),
+        }
+    }
+}
+impl std::error::Error for LoadError {}
+
+#[derive(Clone, Debug)]
+pub struct SearchPaths {
+    dirs: Vec<PathBuf>,
+}
+impl SearchPaths {
+    pub fn new() -> Self {
+        Self { dirs: Vec::new() }
+    }
+    pub fn add(&mut self, dir: impl Into<PathBuf>) {
+        self.dirs.push(dir.into());
+    }
+    pub fn relative_to(&self, dir: &Path) -> Self {
+        let mut dirs = vec![dir.to_path_buf()];
+        dirs.extend(self.dirs.iter().cloned());
+        Self { dirs }
+    }
+    pub fn resolve(&self, filename: &str) -> Option<PathBuf> {
+        let path = Path::new(filename);
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+    }
+}
+impl Default for SearchPaths {
+    fn default() -> Self {
+        let mut search_paths = Self::new();
+        if let Ok(dir) = std::env::var("RTW_IMAGES") {
+            if !dir.is_empty() {
+                search_paths.add(dir);
+            }
+        }
+        search_paths.add("");
+        search_paths.add("assets");
+        let mut images = PathBuf::from("images");
+        for _ in 0..6 {
+            search_paths.add(images.clone());
+            images = Path::new("..").join(images);
+        }
+        search_paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_paths_prefer_earlier_directories() {
+        let root = std::env::temp_dir().join(format!("raytracer-{}-search", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        std::fs::write(first.join("shared.txt"), "").unwrap();
+        std::fs::write(second.join("shared.txt"), "").unwrap();
+        std::fs::write(second.join("only.txt"), "").unwrap();
+        let mut paths = SearchPaths::new();
+        paths.add(&first);
+        paths.add(&second);
+        assert_eq!(paths.resolve("shared.txt"), Some(first.join("shared.txt")));
+        assert_eq!(paths.resolve("only.txt"), Some(second.join("only.txt")));
+        assert_eq!(paths.resolve("absent.txt"), None);
+        // 绝对路径不经过搜索目录; relative_to 把模型目录放在最前
+        let absolute = second.join("only.txt");
+        assert_eq!(
+            SearchPaths::new().resolve(&absolute.to_string_lossy()),
+            Some(absolute)
+        );
+        assert_eq!(
+            paths.relative_to(&second).resolve("shared.txt"),
+            Some(second.join("shared.txt"))
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn errors_name_the_file_and_cause() {
+        let messages = [
+            (
+                LoadError::MissingFile("a.obj".into()),
+                "could not find file \"a.obj\"",
+            ),
+            (
+                LoadError::Parse {
+                    path: PathBuf::from("m/b.ply"),
+                    message: "invalid value".into(),
+                },
+                "failed to parse m/b.ply: invalid value",
+            ),
+            (
+                LoadError::BadMtl {
+                    path: PathBuf::from("c.obj"),
+                    message: "missing".into(),
+                },
+                "failed to load materials of c.obj: missing",
+            ),
+            (
+                LoadError::MissingTexture("d.png".into()),
+                "could not load texture \"d.png\"",
+            ),
+            (
+                LoadError::MaterialOutOfRange {
+                    material: 5,
+                    count: 2,
+                },
+                "material index 5 is out of range (2 materials)",
+            ),
+        ];
+        for (err, expected) in messages {
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+}
@@ -3,93 +3,844 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::hittable_list::HittableList;
 use crate::interval::Interval;
 use crate::ray::Ray;
-use std::cmp::Ordering;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+use rayon::prelude::*;
+use std::fmt;
 use std::sync::Arc;
 
-pub struct BvhNode {
-    left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f64 = 1.0;
+const INTERSECTION_COST: f64 = 1.0;
+const STACK_SIZE: usize = 64;
+const PARALLEL_THRESHOLD: usize = 4096;
+const MORTON_BITS: u32 = 10;
+
+#[derive(Clone, Copy)]
+pub enum BvhBuilder {
+    // 分桶 SAH 降低单个网格的期望求交代价, 但对 final_scene 的整体渲染时间没有可测的影响
+    Sah,
+    Lbvh,
+}
+
+const EMPTY_CHILD: u32 = u32::MAX;
+
+// 结构体数组布局: 一次测试 WIDTH 个子节点包围盒
+#[derive(Clone, Copy)]
+struct WideBvhNode<const WIDTH: usize> {
+    min: [[f64; WIDTH]; 3],
+    max: [[f64; WIDTH]; 3],
+    // 叶子: 第一个图元的下标; 内部节点: 子节点的下标
+    child: [u32; WIDTH],
+    // 为 0 表示内部节点
+    primitive_count: [u32; WIDTH],
+}
+impl<const WIDTH: usize> WideBvhNode<WIDTH> {
+    fn empty() -> Self {
+        Self {
+            min: [[f64::INFINITY; WIDTH]; 3],
+            max: [[f64::NEG_INFINITY; WIDTH]; 3],
+            child: [EMPTY_CHILD; WIDTH],
+            primitive_count: [0; WIDTH],
+        }
+    }
+    fn set_bounds(&mut self, lane: usize, bbox: &Aabb) {
+        for axis in 0..3 {
+            let interval = bbox.axis_interval(axis as u32);
+            self.min[axis][lane] = interval.min;
+            self.max[axis][lane] = interval.max;
+        }
+    }
+    fn bounds(&self) -> Aabb {
+        (0..WIDTH)
+            .filter(|&lane| self.child[lane] != EMPTY_CHILD)
+            .fold(*AABB_EMPTY, |bbox, lane| {
+                Aabb::new_aabb(&bbox, &self.lane_bounds(lane))
+            })
+    }
+    fn lane_bounds(&self, lane: usize) -> Aabb {
+        Aabb {
+            x: Interval::new(self.min[0][lane], self.max[0][lane]),
+            y: Interval::new(self.min[1][lane], self.max[1][lane]),
+            z: Interval::new(self.min[2][lane], self.max[2][lane]),
+        }
+    }
+    fn hit_lanes(&self, origin: &[f64; 3], inv_dir: &[f64; 3], ray_t: &Interval) -> [f64; WIDTH] {
+        let mut t_min = [ray_t.min; WIDTH];
+        let mut t_max = [ray_t.max; WIDTH];
+        for axis in 0..3 {
+            for lane in 0..WIDTH {
+                let t0 = (self.min[axis][lane] - origin[axis]) * inv_dir[axis];
+                let t1 = (self.max[axis][lane] - origin[axis]) * inv_dir[axis];
+                t_min[lane] = t_min[lane].max(t0.min(t1));
+                t_max[lane] = t_max[lane].min(t0.max(t1));
+            }
+        }
+        for lane in 0..WIDTH {
+            if t_min[lane] >= t_max[lane] || self.child[lane] == EMPTY_CHILD {
+                t_min[lane] = f64::INFINITY;
+            }
+        }
+        t_min
+    }
+}
+
+enum BuildNode {
+    Interior {
+        bbox: Aabb,
+        children: Box<(BuildNode, BuildNode)>,
+    },
+    Leaf {
+        bbox: Aabb,
+        first: usize,
+        count: usize,
+    },
+}
+
+struct BuildPrimitive {
+    index: usize,
     bbox: Aabb,
+    centroid: Vec3,
 }
-impl BvhNode {
-    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis_index: u32) -> Ordering {
-        a.bounding_box()
-            .axis_interval(axis_index)
-            .min
-            .partial_cmp(&b.bounding_box().axis_interval(axis_index).min)
-            .unwrap()
-    }
-    fn box_x_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 0)
-    }
-    fn box_y_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 1)
-    }
-    fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 2)
-    }
-    pub fn new(src_objects: &mut Vec<Arc<dyn Hittable>>, s: usize, e: usize) -> Self {
-        let mut bbox = *AABB_EMPTY;
-        for item in src_objects.iter().take(e).skip(s) {
-            bbox = Aabb::new_aabb(&bbox, &(item.bounding_box()));
-        }
-        let axis = bbox.longest_axis();
-        let comparator = match axis {
-            0 => Self::box_x_compare,
-            1 => Self::box_y_compare,
-            _ => Self::box_z_compare,
-        };
-        let objects = src_objects;
-        let object_span = e - s;
-        if object_span == 1 {
-            BvhNode {
-                left: objects[s].clone(),
-                right: objects[s].clone(),
+
+pub struct Bvh<T: Hittable = Arc<dyn Hittable>, const WIDTH: usize = 4> {
+    nodes: Vec<WideBvhNode<WIDTH>>,
+    primitives: Vec<T>,
+}
+
+#[derive(Default)]
+pub struct BvhStats {
+    pub primitive_count: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_leaf_size: usize,
+    pub max_depth: usize,
+    pub sah_cost: f64,
+}
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BVH: {} primitives, {} nodes, {} leaves (at most {} primitives), depth {}, SAH cost {:.2}",
+            self.primitive_count,
+            self.node_count,
+            self.leaf_count,
+            self.max_leaf_size,
+            self.max_depth,
+            self.sah_cost
+        )
+    }
+}
+
+impl Bvh {
+    pub fn new_list(list: &HittableList) -> Self {
+        Self::new(list.objects.clone())
+    }
+}
+impl<T: Hittable, const WIDTH: usize> Bvh<T, WIDTH> {
+    pub fn new(primitives: Vec<T>) -> Self {
+        Self::with_builder(primitives, BvhBuilder::Sah)
+    }
+    pub fn with_builder(primitives: Vec<T>, builder: BvhBuilder) -> Self {
+        const { assert!(WIDTH >= 2) };
+        // 空包围盒 (如不相交的 CSG 交集) 不可能被击中, 不参与构建
+        let mut build_primitives: Vec<BuildPrimitive> = primitives
+            .par_iter()
+            .enumerate()
+            .map(|(index, primitive)| (index, primitive.bounding_box()))
+            .filter(|(_, bbox)| !bbox.is_empty())
+            .map(|(index, bbox)| BuildPrimitive {
+                index,
                 bbox,
-            }
-        } else if object_span == 2 {
-            if comparator(&objects[s], &objects[s + 1]) == Ordering::Less {
-                BvhNode {
-                    left: objects[s].clone(),
-                    right: objects[s + 1].clone(),
-                    bbox,
+                centroid: bbox.centroid(),
+            })
+            .collect();
+        let mut nodes = Vec::with_capacity(2 * primitives.len());
+        if !build_primitives.is_empty() {
+            let root = match builder {
+                BvhBuilder::Sah => build_sah(&mut build_primitives, 0, 1),
+                BvhBuilder::Lbvh => build_lbvh(&mut build_primitives),
+            };
+            collapse(&root, &mut nodes, 1);
+        }
+        let mut slots: Vec<Option<T>> = primitives.into_iter().map(Some).collect();
+        let primitives = build_primitives
+            .iter()
+            .map(|primitive| slots[primitive.index].take().unwrap())
+            .collect();
+        Self { nodes, primitives }
+    }
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats::default();
+        let Some(root) = self.nodes.first() else {
+            return stats;
+        };
+        let root_area = root.bounds().surface_area().max(f64::MIN_POSITIVE);
+        let mut stack = vec![(0, 1)];
+        while let Some((node_index, depth)) = stack.pop() {
+            let node = &self.nodes[node_index];
+            stats.node_count += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.sah_cost += TRAVERSAL_COST * node.bounds().surface_area() / root_area;
+            for lane in 0..WIDTH {
+                let count = node.primitive_count[lane] as usize;
+                if node.child[lane] == EMPTY_CHILD {
+                    continue;
+                } else if count == 0 {
+                    stack.push((node.child[lane] as usize, depth + 1));
+                } else {
+                    let area_ratio = node.lane_bounds(lane).surface_area() / root_area;
+                    stats.leaf_count += 1;
+                    stats.primitive_count += count;
+                    stats.max_leaf_size = stats.max_leaf_size.max(count);
+                    stats.sah_cost += INTERSECTION_COST * count as f64 * area_ratio;
                 }
-            } else {
-                BvhNode {
-                    left: objects[s + 1].clone(),
-                    right: objects[s].clone(),
-                    bbox,
+            }
+        }
+        stats
+    }
+}
+type Bins = [[(usize, Aabb); BIN_COUNT]; 3];
+
+fn point_bounds(p: &Vec3) -> Aabb {
+    Aabb {
+        x: Interval::new(p.x, p.x),
+        y: Interval::new(p.y, p.y),
+        z: Interval::new(p.z, p.z),
+    }
+}
+
+fn bounds(primitives: &[BuildPrimitive]) -> (Aabb, Aabb) {
+    let empty = (*AABB_EMPTY, *AABB_EMPTY);
+    let add = |(bbox, centroid_bounds): (Aabb, Aabb), primitive: &BuildPrimitive| {
+        (
+            Aabb::new_aabb(&bbox, &primitive.bbox),
+            Aabb::new_aabb(&centroid_bounds, &point_bounds(&primitive.centroid)),
+        )
+    };
+    if primitives.len() > PARALLEL_THRESHOLD {
+        primitives.par_iter().fold(|| empty, add).reduce(
+            || empty,
+            |a, b| (Aabb::new_aabb(&a.0, &b.0), Aabb::new_aabb(&a.1, &b.1)),
+        )
+    } else {
+        primitives.iter().fold(empty, add)
+    }
+}
+
+fn bin_index(centroid: f64, interval: &Interval) -> usize {
+    (((centroid - interval.min) / interval.size() * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+}
+
+fn bin_primitives(primitives: &[BuildPrimitive], centroid_bounds: &Aabb) -> Bins {
+    let add = |bins: &mut Bins, primitive: &BuildPrimitive| {
+        for (axis, axis_bins) in bins.iter_mut().enumerate() {
+            let interval = centroid_bounds.axis_interval(axis as u32);
+            if interval.max > interval.min {
+                let bin = bin_index(primitive.centroid.axis(axis as u32), interval);
+                axis_bins[bin].0 += 1;
+                axis_bins[bin].1 = Aabb::new_aabb(&axis_bins[bin].1, &primitive.bbox);
+            }
+        }
+    };
+    let bin_chunk = |chunk: &[BuildPrimitive]| {
+        let mut bins: Bins = [[(0, *AABB_EMPTY); BIN_COUNT]; 3];
+        for primitive in chunk {
+            add(&mut bins, primitive);
+        }
+        bins
+    };
+    if primitives.len() > PARALLEL_THRESHOLD {
+        primitives
+            .par_chunks(PARALLEL_THRESHOLD)
+            .map(bin_chunk)
+            .reduce(
+                || [[(0, *AABB_EMPTY); BIN_COUNT]; 3],
+                |mut a, b| {
+                    for axis in 0..3 {
+                        for bin in 0..BIN_COUNT {
+                            a[axis][bin].0 += b[axis][bin].0;
+                            a[axis][bin].1 = Aabb::new_aabb(&a[axis][bin].1, &b[axis][bin].1);
+                        }
+                    }
+                    a
+                },
+            )
+    } else {
+        bin_chunk(primitives)
+    }
+}
+
+fn sah_split(
+    primitives: &mut [BuildPrimitive],
+    bbox: &Aabb,
+    centroid_bounds: &Aabb,
+) -> Option<usize> {
+    let object_span = primitives.len();
+    if object_span <= 1 {
+        return None;
+    }
+
+    // 分桶 SAH: 每个轴上扫描 BIN_COUNT - 1 个候选分割面
+    let bins = bin_primitives(primitives, centroid_bounds);
+    let parent_area = bbox.surface_area().max(f64::MIN_POSITIVE);
+    let mut best: Option<(usize, usize, f64)> = None;
+    for (axis, axis_bins) in bins.iter().enumerate() {
+        let mut right_areas = [0.0; BIN_COUNT];
+        let mut right_counts = [0usize; BIN_COUNT];
+        let mut right_box = *AABB_EMPTY;
+        let mut right_count = 0;
+        for bin in (1..BIN_COUNT).rev() {
+            right_box = Aabb::new_aabb(&right_box, &axis_bins[bin].1);
+            right_count += axis_bins[bin].0;
+            right_areas[bin] = right_box.surface_area();
+            right_counts[bin] = right_count;
+        }
+        let mut left_box = *AABB_EMPTY;
+        let mut left_count = 0;
+        for bin in 0..BIN_COUNT - 1 {
+            left_box = Aabb::new_aabb(&left_box, &axis_bins[bin].1);
+            left_count += axis_bins[bin].0;
+            if left_count == 0 || right_counts[bin + 1] == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + INTERSECTION_COST
+                    * (left_box.surface_area() * left_count as f64
+                        + right_areas[bin + 1] * right_counts[bin + 1] as f64)
+                    / parent_area;
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, bin, cost));
+            }
+        }
+    }
+
+    let leaf_cost = INTERSECTION_COST * object_span as f64;
+    match best {
+        Some((axis, split, cost)) if cost < leaf_cost || object_span > MAX_LEAF_SIZE => {
+            let interval = centroid_bounds.axis_interval(axis as u32);
+            let mut mid = 0;
+            for i in 0..object_span {
+                if bin_index(primitives[i].centroid.axis(axis as u32), interval) <= split {
+                    primitives.swap(i, mid);
+                    mid += 1;
                 }
             }
+            Some(mid)
+        }
+        // 质心全部重合时无法分桶, 只能对半分
+        None if object_span > MAX_LEAF_SIZE => Some(object_span / 2),
+        _ => None,
+    }
+}
+
+fn build_children(
+    primitives: &mut [BuildPrimitive],
+    mid: usize,
+    build: impl Fn(&mut [BuildPrimitive], usize) -> BuildNode + Sync,
+) -> (BuildNode, BuildNode) {
+    let parallel = primitives.len() > PARALLEL_THRESHOLD;
+    let (left, right) = primitives.split_at_mut(mid);
+    if parallel {
+        rayon::join(|| build(left, 0), || build(right, mid))
+    } else {
+        (build(left, 0), build(right, mid))
+    }
+}
+
+fn build_sah(primitives: &mut [BuildPrimitive], first: usize, depth: usize) -> BuildNode {
+    let (bbox, centroid_bounds) = bounds(primitives);
+    let split = if depth < STACK_SIZE {
+        sah_split(primitives, &bbox, &centroid_bounds)
+    } else {
+        None
+    };
+    match split {
+        Some(mid) => BuildNode::Interior {
+            bbox,
+            children: Box::new(build_children(primitives, mid, |half, offset| {
+                build_sah(half, first + offset, depth + 1)
+            })),
+        },
+        None => BuildNode::Leaf {
+            bbox,
+            first,
+            count: primitives.len(),
+        },
+    }
+}
+
+fn expand_bits(v: u32) -> u32 {
+    let mut v = v & 0x3ff;
+    v = (v | (v << 16)) & 0x030000ff;
+    v = (v | (v << 8)) & 0x0300f00f;
+    v = (v | (v << 4)) & 0x030c30c3;
+    v = (v | (v << 2)) & 0x09249249;
+    v
+}
+
+fn morton_code(p: &Vec3, centroid_bounds: &Aabb) -> u32 {
+    let scale = (1 << MORTON_BITS) as f64;
+    let quantize = |axis: u32| {
+        let interval = centroid_bounds.axis_interval(axis);
+        if interval.max > interval.min {
+            ((p.axis(axis) - interval.min) / interval.size() * scale).clamp(0.0, scale - 1.0) as u32
         } else {
-            (*objects)[s..e].sort_by(comparator);
-            let mid = s + object_span / 2;
-            let left = Arc::new(Self::new(objects, s, mid));
-            let right = Arc::new(Self::new(objects, mid, e));
-            Self { left, right, bbox }
+            0
         }
+    };
+    (expand_bits(quantize(0)) << 2) | (expand_bits(quantize(1)) << 1) | expand_bits(quantize(2))
+}
+
+fn build_lbvh(primitives: &mut [BuildPrimitive]) -> BuildNode {
+    let (_, centroid_bounds) = bounds(primitives);
+    primitives
+        .par_sort_by_cached_key(|primitive| morton_code(&primitive.centroid, &centroid_bounds));
+    let codes: Vec<u32> = primitives
+        .par_iter()
+        .map(|primitive| morton_code(&primitive.centroid, &centroid_bounds))
+        .collect();
+    emit_lbvh(primitives, &codes, 0, 3 * MORTON_BITS as i32 - 1, 1)
+}
+
+fn emit_lbvh(
+    primitives: &mut [BuildPrimitive],
+    codes: &[u32],
+    first: usize,
+    bit: i32,
+    depth: usize,
+) -> BuildNode {
+    let (bbox, _) = bounds(primitives);
+    if primitives.len() <= MAX_LEAF_SIZE || depth >= STACK_SIZE {
+        return BuildNode::Leaf {
+            bbox,
+            first,
+            count: primitives.len(),
+        };
     }
-    pub fn new_list(list: &mut HittableList) -> Self {
-        let len = list.objects.len();
-        Self::new(&mut list.objects, 0, len)
+    // 编码已排序, 首尾编码第一个不同的位即为分割位
+    let last = codes.len() - 1;
+    let mut bit = bit;
+    while bit >= 0 && (codes[0] >> bit) & 1 == (codes[last] >> bit) & 1 {
+        bit -= 1;
+    }
+    let mid = if bit >= 0 {
+        codes.partition_point(|code| (code >> bit) & 1 == 0)
+    } else {
+        primitives.len() / 2
+    };
+    BuildNode::Interior {
+        bbox,
+        children: Box::new(build_children(primitives, mid, |half, offset| {
+            emit_lbvh(
+                half,
+                &codes[offset..offset + half.len()],
+                first + offset,
+                bit - 1,
+                depth + 1,
+            )
+        })),
     }
 }
-impl Hittable for BvhNode {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        let ray_t = *ray_t;
-        if !self.bbox.hit(r, &ray_t) {
-            return false;
+
+impl BuildNode {
+    fn bbox(&self) -> &Aabb {
+        match self {
+            BuildNode::Interior { bbox, .. } | BuildNode::Leaf { bbox, .. } => bbox,
         }
-        let hit_left = self.left.hit(r, &ray_t, rec);
-        let hit_right = if hit_left {
-            self.right.hit(r, &Interval::new(ray_t.min, rec.t), rec)
-        } else {
-            self.right.hit(r, &ray_t, rec)
+    }
+    // 子树的图元在重排后是连续的一段
+    fn primitive_range(&self) -> (usize, usize) {
+        match self {
+            BuildNode::Interior { children, .. } => {
+                let (first, left_count) = children.0.primitive_range();
+                (first, left_count + children.1.primitive_range().1)
+            }
+            BuildNode::Leaf { first, count, .. } => (*first, *count),
+        }
+    }
+}
+
+// 把二叉树折叠成 WIDTH 叉树: 反复展开表面积最大的内部子节点.
+// 每层最多净压栈 WIDTH - 1 项, 超过深度上限的子树直接合并成一个叶子
+fn collapse<const WIDTH: usize>(
+    node: &BuildNode,
+    nodes: &mut Vec<WideBvhNode<WIDTH>>,
+    depth: usize,
+) -> usize {
+    let mut children = match node {
+        BuildNode::Interior { children, .. } => vec![&children.0, &children.1],
+        BuildNode::Leaf { .. } => vec![node],
+    };
+    while children.len() < WIDTH {
+        let Some((i, _)) = children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| matches!(child, BuildNode::Interior { .. }))
+            .max_by(|(_, a), (_, b)| a.bbox().surface_area().total_cmp(&b.bbox().surface_area()))
+        else {
+            break;
         };
-        hit_left || hit_right
+        if let BuildNode::Interior {
+            children: grandchildren,
+            ..
+        } = children.swap_remove(i)
+        {
+            children.push(&grandchildren.0);
+            children.push(&grandchildren.1);
+        }
+    }
+    let node_index = nodes.len();
+    nodes.push(WideBvhNode::empty());
+    for (lane, child) in children.into_iter().enumerate() {
+        let (offset, count) = match child {
+            BuildNode::Interior { .. } if depth < (STACK_SIZE - 1) / (WIDTH - 1) => {
+                (collapse(child, nodes, depth + 1), 0)
+            }
+            _ => child.primitive_range(),
+        };
+        let wide = &mut nodes[node_index];
+        wide.set_bounds(lane, child.bbox());
+        wide.child[lane] = offset as u32;
+        wide.primitive_count[lane] = count as u32;
+    }
+    node_index
+}
+
+impl<T: Hittable, const WIDTH: usize> Hittable for Bvh<T, WIDTH> {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let origin = [r.origin.x, r.origin.y, r.origin.z];
+        let inv_dir = [
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        ];
+        // 图元求交失败时也可能改写记录, 先写入临时记录, 命中后再拷贝
+        let mut temp_rec = rec.scratch();
+        let mut hit_anything = false;
+        let mut closest_so_far = ray_t.max;
+        // 栈中保存 (子节点, 图元数, 进入距离)
+        let mut stack = [(0u32, 0u32, 0.0); STACK_SIZE];
+        stack[0] = (0, 0, ray_t.min);
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (child, count, t_enter) = stack[stack_len];
+            if t_enter >= closest_so_far {
+                continue;
+            }
+            if count > 0 {
+                let first = child as usize;
+                for primitive in &self.primitives[first..first + count as usize] {
+                    if primitive.hit(
+                        r,
+                        &Interval::new(ray_t.min, closest_so_far),
+                        &mut temp_rec,
+                        sampler,
+                    ) {
+                        hit_anything = true;
+                        closest_so_far = temp_rec.t;
+                        *rec = temp_rec.clone();
+                    }
+                }
+                continue;
+            }
+            let node = &self.nodes[child as usize];
+            let t_lanes =
+                node.hit_lanes(&origin, &inv_dir, &Interval::new(ray_t.min, closest_so_far));
+            // 按进入距离从远到近压栈, 使最近的子节点先出栈
+            let mut order = [0usize; WIDTH];
+            let mut hit_count = 0;
+            for lane in 0..WIDTH {
+                if t_lanes[lane] == f64::INFINITY {
+                    continue;
+                }
+                let mut k = hit_count;
+                while k > 0 && t_lanes[order[k - 1]] < t_lanes[lane] {
+                    order[k] = order[k - 1];
+                    k -= 1;
+                }
+                order[k] = lane;
+                hit_count += 1;
+            }
+            for &lane in &order[..hit_count] {
+                stack[stack_len] = (node.child[lane], node.primitive_count[lane], t_lanes[lane]);
+                stack_len += 1;
+            }
+        }
+        hit_anything
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let origin = [r.origin.x, r.origin.y, r.origin.z];
+        let inv_dir = [
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        ];
+        // 任意命中即可返回, 无需按距离排序
+        let mut stack = [(0u32, 0u32); STACK_SIZE];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (child, count) = stack[stack_len];
+            if count > 0 {
+                let first = child as usize;
+                if self.primitives[first..first + count as usize]
+                    .iter()
+                    .any(|primitive| primitive.occluded(r, ray_t, sampler))
+                {
+                    return true;
+                }
+                continue;
+            }
+            let node = &self.nodes[child as usize];
+            let t_lanes = node.hit_lanes(&origin, &inv_dir, ray_t);
+            for (lane, &t_lane) in t_lanes.iter().enumerate() {
+                if t_lane != f64::INFINITY {
+                    stack[stack_len] = (node.child[lane], node.primitive_count[lane]);
+                    stack_len += 1;
+                }
+            }
+        }
+        false
     }
 
     fn bounding_box(&self) -> Aabb {
-        self.bbox
+        self.nodes.first().map_or(*AABB_EMPTY, |root| root.bounds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::triangle::{Triangle, TriangleMesh};
+    use crate::utility::INFINITY;
+
+    fn random_spheres(count: usize, sampler: &mut dyn Sampler) -> HittableList {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        for _ in 0..count {
+            let center = Vec3::random_range(-10.0, 10.0, sampler);
+            let radius = 0.1 + sampler.get_1d();
+            list.add(Arc::new(Sphere::new(center, radius, mat.clone())));
+        }
+        list
+    }
+
+    fn triangle_soup(count: usize, sampler: &mut dyn Sampler) -> Arc<TriangleMesh> {
+        let mut mesh = TriangleMesh {
+            materials: vec![Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))],
+            ..TriangleMesh::default()
+        };
+        for face in 0..count as u32 {
+            let center = Vec3::random_range(-10.0, 10.0, sampler);
+            for _ in 0..3 {
+                mesh.positions
+                    .push(center + Vec3::random_range(-1.0, 1.0, sampler));
+            }
+            mesh.indices.push([3 * face, 3 * face + 1, 3 * face + 2]);
+            mesh.face_materials.push(0);
+        }
+        Arc::new(mesh)
+    }
+
+    fn assert_matches_list<T: Hittable, const WIDTH: usize>(
+        bvh: &Bvh<T, WIDTH>,
+        list: &HittableList,
+    ) {
+        let mut sampler = IndependentSampler::new(7);
+        let ray_t = Interval::new(0.001, INFINITY);
+        for _ in 0..2000 {
+            let origin = Vec3::random_range(-15.0, 15.0, &mut sampler);
+            let r = Ray::new(origin, Vec3::random_unit_vector(&mut sampler));
+            let mut expected = HitRecord::default();
+            let mut actual = HitRecord::default();
+            let hit = list.hit(&r, &ray_t, &mut expected, &mut sampler);
+            assert_eq!(bvh.hit(&r, &ray_t, &mut actual, &mut sampler), hit);
+            assert_eq!(bvh.occluded(&r, &ray_t, &mut sampler), hit);
+            if hit {
+                assert_eq!(actual.t, expected.t);
+                assert_eq!(actual.normal.x, expected.normal.x);
+            }
+        }
+    }
+
+    #[test]
+    fn traversal_matches_brute_force() {
+        let mut sampler = IndependentSampler::new(1);
+        let list = random_spheres(300, &mut sampler);
+        for builder in [BvhBuilder::Sah, BvhBuilder::Lbvh] {
+            let bvh4: Bvh = Bvh::with_builder(list.objects.clone(), builder);
+            assert_matches_list(&bvh4, &list);
+            let bvh2: Bvh<Arc<dyn Hittable>, 2> = Bvh::with_builder(list.objects.clone(), builder);
+            assert_matches_list(&bvh2, &list);
+        }
+    }
+
+    // 未命中时也会改写记录的图元
+    struct ScribblingMiss;
+    impl Hittable for ScribblingMiss {
+        fn hit(
+            &self,
+            _r: &Ray,
+            _ray_t: &Interval,
+            rec: &mut HitRecord,
+            _sampler: &mut dyn Sampler,
+        ) -> bool {
+            rec.t = -1.0;
+            rec.normal = Vec3::new(0.0, 0.0, 0.0);
+            false
+        }
+        fn bounding_box(&self) -> Aabb {
+            Aabb::new_points(&Vec3::new(-1.0, -1.0, -1.0), &Vec3::new(1.0, 1.0, 1.0))
+        }
+    }
+
+    #[test]
+    fn missed_primitives_do_not_clobber_the_record() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        list.add(Arc::new(Sphere::new(Vec3::new(0.0, 0.0, -0.5), 0.25, mat)));
+        // 与球同处一个叶节点, 在球之后才被测试
+        for _ in 0..3 {
+            list.add(Arc::new(ScribblingMiss));
+        }
+        let bvh = Bvh::new_list(&list);
+        let r = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(bvh.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+        assert!((rec.t - 5.25).abs() < 1e-9);
+        assert!((rec.normal.z - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn triangle_soups_match_brute_force() {
+        for seed in 0..3 {
+            let mut sampler = IndependentSampler::new(seed);
+            let mesh = triangle_soup(500, &mut sampler);
+            let mut list = HittableList::new();
+            for face in 0..mesh.face_count() {
+                list.add(Arc::new(Triangle::new(&mesh, face)));
+            }
+            for builder in [BvhBuilder::Sah, BvhBuilder::Lbvh] {
+                let triangles = || (0..mesh.face_count()).map(|face| Triangle::new(&mesh, face));
+                let bvh4: Bvh<Triangle, 4> = Bvh::with_builder(triangles().collect(), builder);
+                assert_matches_list(&bvh4, &list);
+                let bvh8: Bvh<Triangle, 8> = Bvh::with_builder(triangles().collect(), builder);
+                assert_matches_list(&bvh8, &list);
+            }
+        }
+    }
+
+    #[test]
+    fn identical_centroids_are_split_in_half() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        for i in 0..100 {
+            let center = Vec3::new(0.0, 0.0, 0.0);
+            list.add(Arc::new(Sphere::new(
+                center,
+                0.1 * (i + 1) as f64,
+                mat.clone(),
+            )));
+        }
+        for builder in [BvhBuilder::Sah, BvhBuilder::Lbvh] {
+            let bvh4: Bvh<Arc<dyn Hittable>, 4> = Bvh::with_builder(list.objects.clone(), builder);
+            let bvh8: Bvh<Arc<dyn Hittable>, 8> = Bvh::with_builder(list.objects.clone(), builder);
+            for stats in [bvh4.stats(), bvh8.stats()] {
+                assert_eq!(stats.primitive_count, 100);
+                assert!(stats.max_leaf_size <= MAX_LEAF_SIZE);
+            }
+            assert_matches_list(&bvh4, &list);
+            assert_matches_list(&bvh8, &list);
+        }
+    }
+
+    // 按几何级数排列的球, SAH 每次只能剥离最大的一个, 得到一条很深的链
+    fn assert_deep_chain_is_capped<const WIDTH: usize>(list: &HittableList) {
+        let bvh: Bvh<Arc<dyn Hittable>, WIDTH> = Bvh::new(list.objects.clone());
+        let stats = bvh.stats();
+        assert_eq!(stats.primitive_count, list.objects.len());
+        assert!(stats.max_depth <= (STACK_SIZE - 1) / (WIDTH - 1));
+        // 超过深度上限的子树合并成了叶子
+        assert!(stats.max_leaf_size > MAX_LEAF_SIZE);
+        let mut sampler = IndependentSampler::new(0);
+        for (i, object) in list.objects.iter().enumerate() {
+            let bbox = object.bounding_box();
+            let radius = bbox.x.size() / 2.0;
+            let center = bbox.centroid();
+            let r = Ray::new(
+                center + Vec3::new(0.0, 10.0 * radius, 0.0),
+                Vec3::new(0.0, -1.0, 0.0),
+            );
+            let mut rec = HitRecord::default();
+            assert!(bvh.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+            assert!((rec.t / (9.0 * radius) - 1.0).abs() < 1e-9, "sphere {}", i);
+        }
+    }
+
+    #[test]
+    fn deep_chains_become_leaves_at_the_depth_cap() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        for i in 0..200 {
+            let scale = 1.5f64.powi(i);
+            let center = Vec3::new(scale, 0.0, 0.0);
+            list.add(Arc::new(Sphere::new(center, 0.1 * scale, mat.clone())));
+        }
+        assert_deep_chain_is_capped::<4>(&list);
+        assert_deep_chain_is_capped::<8>(&list);
+    }
+
+    #[test]
+    fn hit_lanes_reports_entry_distances() {
+        let mut node = WideBvhNode::<4>::empty();
+        for lane in 0..3 {
+            let z = -2.0 * lane as f64;
+            node.set_bounds(
+                lane,
+                &Aabb::new_points(&Vec3::new(-1.0, -1.0, z - 1.0), &Vec3::new(1.0, 1.0, z)),
+            );
+            node.child[lane] = lane as u32;
+        }
+        // 偏离光线的包围盒
+        node.set_bounds(
+            2,
+            &Aabb::new_points(&Vec3::new(2.0, 2.0, -5.0), &Vec3::new(3.0, 3.0, -4.0)),
+        );
+        node.set_bounds(
+            3,
+            &Aabb::new_points(&Vec3::new(-9.0, -9.0, -9.0), &Vec3::new(9.0, 9.0, 9.0)),
+        );
+        let origin = [0.0, 0.0, 5.0];
+        let inv_dir = [f64::INFINITY, f64::INFINITY, -1.0];
+        let t = node.hit_lanes(&origin, &inv_dir, &Interval::new(0.001, INFINITY));
+        assert_eq!(t[0], 5.0);
+        assert_eq!(t[1], 7.0);
+        assert_eq!(t[2], f64::INFINITY);
+        // 空槽位即使包围盒覆盖光线也不命中
+        assert_eq!(t[3], f64::INFINITY);
+        let t = node.hit_lanes(&origin, &inv_dir, &Interval::new(0.001, 6.0));
+        assert_eq!(t[0], 5.0);
+        assert_eq!(t[1], f64::INFINITY);
     }
 }
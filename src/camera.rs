@@ -1,14 +1,23 @@
-use crate::color::{Color, write_color};
+use crate::color::{Color, luminance};
+use crate::film::Film;
+use crate::filter::{Filter, FilterType};
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::ScatterRecord;
+use crate::medium::MediumStack;
 use crate::pdf::{HittablePDF, MixturePDF, Pdf};
 use crate::ray::Ray;
-use crate::utility::{INFINITY, degrees_to_radians, random_double};
+use crate::sampler::{
+    HaltonSampler, IndependentSampler, Sampler, SamplerType, SobolSampler, StratifiedSampler,
+    ZSobolSampler,
+};
+use crate::utility::{INFINITY, degrees_to_radians};
 use crate::vec3::Vec3;
 use image::{ImageBuffer, RgbImage};
+use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 #[derive(Default)]
 pub struct Camera {
@@ -20,7 +29,6 @@ pub struct Camera {
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
     pub samples_per_pixel: u32,
-    pixel_sample_scale: f64,
     pub max_depth: i32,
     pub vfov: f64,
     pub lookfrom: Vec3,
@@ -34,8 +42,77 @@ pub struct Camera {
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
     pub background: Color,
-    sqrt_spp: u32,
-    recip_sqrt_spp: f64,
+    pub seed: u64,
+    pub sampler_type: SamplerType,
+    pub adaptive_threshold: f64,
+    pub filter_type: FilterType,
+    pub filter_radius: f64,
+}
+
+const ADAPTIVE_MIN_SAMPLES: u32 = 64;
+const ADAPTIVE_BATCH_SIZE: u32 = 16;
+const ADAPTIVE_LUMINANCE_FLOOR: f64 = 0.01;
+const TILE_SIZE: u32 = 32;
+const MAX_FALSE_HIT_CROSSINGS: u32 = 64;
+const MAX_RANDOM_WALK_STEPS: u32 = 1 << 16;
+
+struct Tile {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+// 从图像中心向外按环螺旋排列, 先完成画面主体
+fn spiral_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let cx = (tiles_x - 1) as f64 / 2.0;
+    let cy = (tiles_y - 1) as f64 / 2.0;
+    let mut keyed: Vec<((f64, f64), Tile)> = (0..tiles_y)
+        .flat_map(|ty| (0..tiles_x).map(move |tx| (tx, ty)))
+        .map(|(tx, ty)| {
+            let dx = tx as f64 - cx;
+            let dy = ty as f64 - cy;
+            let ring = dx.abs().max(dy.abs()).round();
+            let tile = Tile {
+                x0: tx * TILE_SIZE,
+                y0: ty * TILE_SIZE,
+                x1: ((tx + 1) * TILE_SIZE).min(width),
+                y1: ((ty + 1) * TILE_SIZE).min(height),
+            };
+            ((ring, dy.atan2(dx)), tile)
+        })
+        .collect();
+    keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    keyed.into_iter().map(|(_, tile)| tile).collect()
+}
+
+// 均值的标准误差换算到 gamma 空间 (sqrt) 的误差, 暗像素以下限代替均值
+fn relative_error(sum: f64, sum_squares: f64, n: u32) -> f64 {
+    let n = n as f64;
+    let mean = sum / n;
+    let variance = ((sum_squares - sum * mean) / (n - 1.0)).max(0.0);
+    (variance / n).sqrt() / (2.0 * mean.max(ADAPTIVE_LUMINANCE_FLOOR).sqrt())
+}
+
+// 误差估计只统计有限样本: 被丢弃的 NaN/Inf 样本不计入样本数
+#[derive(Default)]
+struct PixelStats {
+    accepted: u32,
+    luminance_sum: f64,
+    luminance_squares: f64,
+}
+impl PixelStats {
+    fn add(&mut self, sample_luminance: f64) {
+        self.accepted += 1;
+        self.luminance_sum += sample_luminance;
+        self.luminance_squares += sample_luminance * sample_luminance;
+    }
+    fn converged(&self, min_samples: u32, threshold: f64) -> bool {
+        self.accepted >= min_samples.max(2)
+            && relative_error(self.luminance_sum, self.luminance_squares, self.accepted) < threshold
+    }
 }
 
 impl Camera {
@@ -44,9 +121,19 @@ impl Camera {
         if self.image_height < 1 {
             self.image_height = 1;
         }
-        self.sqrt_spp = self.samples_per_pixel.isqrt();
-        self.pixel_sample_scale = 1.0 / (self.sqrt_spp * self.sqrt_spp) as f64;
-        self.recip_sqrt_spp = 1.0 / self.sqrt_spp as f64;
+        self.samples_per_pixel = self.samples_per_pixel.max(1);
+        if let Ok(name) = std::env::var("RTW_SAMPLER") {
+            match SamplerType::from_name(&name) {
+                Some(sampler_type) => self.sampler_type = sampler_type,
+                None => eprintln!("Unknown sampler \"{}\", using {}", name, self.sampler_type),
+            }
+        }
+        if let Ok(name) = std::env::var("RTW_FILTER") {
+            match FilterType::from_name(&name) {
+                Some(filter_type) => self.filter_type = filter_type,
+                None => eprintln!("Unknown filter \"{}\", using {}", name, self.filter_type),
+            }
+        }
         self.center = self.lookfrom;
         let theta = degrees_to_radians(self.vfov);
         let h = (theta / 2.0).tan();
@@ -66,34 +153,44 @@ impl Camera {
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
     }
-    // fn sample_square() -> Vec3 {
-    //     Vec3::new(random_double() - 0.5, random_double() - 0.5, 0.0)
-    // }
-    fn sample_square_stratified(&self, s_i: u32, s_j: u32) -> Vec3 {
-        let px = ((s_i as f64 + random_double()) * self.recip_sqrt_spp) - 0.5;
-        let py = ((s_j as f64 + random_double()) * self.recip_sqrt_spp) - 0.5;
-        Vec3 {
-            x: px,
-            y: py,
-            z: 0.0,
+    fn make_sampler(&self) -> Box<dyn Sampler> {
+        let spp = self.samples_per_pixel;
+        match self.sampler_type {
+            SamplerType::Independent => Box::new(IndependentSampler::new(self.seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(spp, self.seed)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(self.seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(spp, self.seed)),
+            SamplerType::BlueNoise => Box::new(ZSobolSampler::new(
+                spp,
+                self.image_width,
+                self.image_height,
+                self.seed,
+            )),
         }
     }
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let p = Vec3::random_unit_vector();
+    fn sample_square(sampler: &mut dyn Sampler) -> Vec3 {
+        let (px, py) = sampler.get_2d();
+        Vec3::new(px - 0.5, py - 0.5, 0.0)
+    }
+    // 预算不足最小样本数时按预算的一半停止检查, 但至少一批
+    fn adaptive_min_samples(&self) -> u32 {
+        ADAPTIVE_MIN_SAMPLES
+            .min(self.samples_per_pixel / 2)
+            .max(ADAPTIVE_BATCH_SIZE)
+    }
+    fn defocus_disk_sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let p = Vec3::random_in_unit_disk(sampler);
         self.center + (self.defocus_disk_u * p.x) + (self.defocus_disk_v * p.y)
     }
-    fn get_ray(&self, i: u32, j: u32, s_i: u32, s_j: u32) -> Ray {
-        let offset = self.sample_square_stratified(s_i, s_j);
-        let pixel_sample = self.pixel00_loc
-            + (self.pixel_delta_u * (i as f64 + offset.x))
-            + (self.pixel_delta_v * (j as f64 + offset.y));
+    fn get_ray(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Ray {
+        let pixel_sample = self.pixel00_loc + (self.pixel_delta_u * x) + (self.pixel_delta_v * y);
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(sampler)
         };
         let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random_double();
+        let ray_time = sampler.get_1d();
         Ray::new_time(ray_origin, ray_direction, ray_time)
     }
     fn ray_color(
@@ -102,31 +199,190 @@ impl Camera {
         depth: i32,
         world: &Arc<dyn Hittable>,
         lights: &Arc<dyn Hittable>,
+        media: &MediumStack,
+        sampler: &mut dyn Sampler,
     ) -> Color {
         if depth <= 0 {
             return Color::new(0.0, 0.0, 0.0);
         }
-        let mut rec: HitRecord = HitRecord::default();
-        if !world.hit(r, &Interval::new(0.001, INFINITY), &mut rec) {
-            return self.background;
-        }
-        let mut srec = ScatterRecord::default();
-        let color_from_emission = rec.mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
-        if !rec.mat.scatter(r, &rec, &mut srec) {
-            return color_from_emission;
+        // 穿过低优先级介质表面 (假命中) 时沿原方向步进, 不消耗 depth, 但次数单独受限.
+        // 介质内的随机游走同样在循环中迭代, 使用独立的步数上限, 不占用表面反弹深度
+        let mut crossed: Option<(Ray, MediumStack)> = None;
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut crossings = 0;
+        let mut walk_steps = 0;
+        // 游走散射点严格位于介质内部, 离界面可能比自相交容差还近; 从散射点出发的光线不设容差,
+        // 否则会漏掉出射界面, 带着介质跑到外面并在重新进入时重复压栈
+        let mut t_min = 0.001;
+        loop {
+            let (rec, transmittance) = loop {
+                let (r, media) = match &crossed {
+                    Some((r, media)) => (r, media),
+                    None => (r, media),
+                };
+                let mut rec: HitRecord = HitRecord::default();
+                let hit = world.hit(r, &Interval::new(t_min, INFINITY), &mut rec, sampler);
+                let ray_length = r.direction.length();
+                let distance = if hit { rec.t * ray_length } else { INFINITY };
+                let transmittance = match media.sample_distance(distance, sampler) {
+                    Some((Some(scatter_distance), weight)) => {
+                        walk_steps += 1;
+                        if walk_steps > MAX_RANDOM_WALK_STEPS {
+                            return Color::new(0.0, 0.0, 0.0);
+                        }
+                        let scattered = Ray::new_time(
+                            r.at(scatter_distance / ray_length),
+                            Vec3::random_unit_vector(sampler),
+                            r.tm,
+                        );
+                        let next_media = media.scattered(&weight);
+                        throughput = throughput * weight;
+                        t_min = 0.0;
+                        crossed = Some((scattered, next_media));
+                        continue;
+                    }
+                    Some((None, weight)) => weight,
+                    None => media.transmittance(distance),
+                };
+                if !hit {
+                    return throughput * self.background * transmittance;
+                }
+                match rec.mat.medium() {
+                    Some(medium) if media.is_false_hit(medium) => {
+                        crossings += 1;
+                        if crossings > MAX_FALSE_HIT_CROSSINGS {
+                            return Color::new(0.0, 0.0, 0.0);
+                        }
+                        let mut inner = media.clone();
+                        inner.cross(medium, rec.front_face);
+                        throughput = throughput * transmittance;
+                        t_min = 0.001;
+                        crossed = Some((Ray::new_time(rec.p, r.direction, r.tm), inner));
+                    }
+                    _ => break (rec, throughput * transmittance),
+                }
+            };
+            let (r, media) = match &crossed {
+                Some((r, media)) => (r, media),
+                None => (r, media),
+            };
+            let mut srec = ScatterRecord::default();
+            let color_from_emission = rec.mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
+            let scattered = match rec.mat.medium() {
+                Some(medium) => {
+                    let eta = media.eta(medium, rec.front_face);
+                    rec.mat.scatter_interface(r, &rec, &mut srec, eta, sampler)
+                }
+                None => rec.mat.scatter(r, &rec, &mut srec, sampler),
+            };
+            if !scattered {
+                return transmittance * color_from_emission;
+            }
+            if srec.skip_pdf {
+                let next_media = match rec.mat.medium() {
+                    Some(medium)
+                        if srec.skip_pdf_ray.direction.dot(&rec.geometric_normal) < 0.0 =>
+                    {
+                        let mut next_media = media.clone();
+                        next_media.cross(medium, rec.front_face);
+                        Some(next_media)
+                    }
+                    _ => None,
+                };
+                // 在散射介质内被界面反射回来时仍属于同一次随机游走, 计入游走步数而不消耗 depth
+                if next_media.is_none()
+                    && color_from_emission.near_zero()
+                    && media.current().is_some_and(|medium| medium.is_scattering())
+                {
+                    walk_steps += 1;
+                    if walk_steps > MAX_RANDOM_WALK_STEPS {
+                        return Color::new(0.0, 0.0, 0.0);
+                    }
+                    throughput = transmittance * srec.attenuation;
+                    let inner = media.clone();
+                    t_min = 0.001;
+                    crossed = Some((srec.skip_pdf_ray, inner));
+                    continue;
+                }
+                let next_media = next_media.as_ref().unwrap_or(media);
+                return transmittance
+                    * (color_from_emission
+                        + srec.attenuation
+                            * self.ray_color(
+                                &srec.skip_pdf_ray,
+                                depth - 1,
+                                world,
+                                lights,
+                                next_media,
+                                sampler,
+                            ));
+            }
+            // 光源方向与 BSDF 方向混合采样, 可见性由下面的完整求交决定 (不使用 occluded)
+            let light_ptr = Arc::new(HittablePDF::new(lights, &rec.p));
+            let p = MixturePDF::new(light_ptr, srec.pdf_ptr);
+            let scattered = Ray::new_time(rec.p, p.generate(sampler), r.tm);
+            let pdf_value = p.value(&scattered.direction, sampler);
+            let scattering_pdf = rec.mat.scattering_pdf(r, &rec, &scattered);
+            let sample_color = self.ray_color(&scattered, depth - 1, world, lights, media, sampler);
+            let color_from_scatter = (srec.attenuation * scattering_pdf * sample_color) / pdf_value;
+            return transmittance * (color_from_emission + color_from_scatter);
         }
-        if srec.skip_pdf {
-            return color_from_emission
-                + srec.attenuation * self.ray_color(&srec.skip_pdf_ray, depth - 1, world, lights);
+    }
+    // 渲染一个块到外扩滤波器半径的局部胶片, 返回内部区域, 外扩条带和追踪的样本数
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        filter: &Filter,
+        min_samples: u32,
+        world: &Arc<dyn Hittable>,
+        lights: &Arc<dyn Hittable>,
+    ) -> (Film, Vec<Film>, u64) {
+        let margin = filter.radius.ceil() as u32;
+        let x0 = tile.x0.saturating_sub(margin);
+        let y0 = tile.y0.saturating_sub(margin);
+        let x1 = (tile.x1 + margin).min(self.image_width);
+        let y1 = (tile.y1 + margin).min(self.image_height);
+        let mut tile_film = Film::new(x0, y0, x1 - x0, y1 - y0);
+        let mut sampler = self.make_sampler();
+        let mut tile_samples = 0u64;
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let mut stats = PixelStats::default();
+                let mut samples = 0;
+                while samples < self.samples_per_pixel {
+                    sampler.start_pixel_sample(i, j, samples);
+                    let offset = Self::sample_square(sampler.as_mut());
+                    let x = i as f64 + offset.x;
+                    let y = j as f64 + offset.y;
+                    let r = self.get_ray(x, y, sampler.as_mut());
+                    let sample_color = self.ray_color(
+                        &r,
+                        self.max_depth,
+                        world,
+                        lights,
+                        &MediumStack::new(),
+                        sampler.as_mut(),
+                    );
+                    // NaN/Inf 样本会溅射到半径内的所有像素, 直接丢弃
+                    let sample_luminance = luminance(&sample_color);
+                    if sample_luminance.is_finite() {
+                        tile_film.add_sample(x, y, &sample_color, filter);
+                        stats.add(sample_luminance);
+                    }
+                    samples += 1;
+                    if self.adaptive_threshold > 0.0
+                        && samples % ADAPTIVE_BATCH_SIZE == 0
+                        && stats.converged(min_samples, self.adaptive_threshold)
+                    {
+                        break;
+                    }
+                }
+                tile_film.add_sample_count(i, j, samples);
+                tile_samples += samples as u64;
+            }
         }
-        let light_ptr = Arc::new(HittablePDF::new(lights, &rec.p));
-        let p = MixturePDF::new(light_ptr, srec.pdf_ptr);
-        let scattered = Ray::new_time(rec.p, p.generate(), r.tm);
-        let pdf_value = p.value(&scattered.direction);
-        let scattering_pdf = rec.mat.scattering_pdf(r, &rec, &scattered);
-        let sample_color = self.ray_color(&scattered, depth - 1, world, lights);
-        let color_from_scatter = (srec.attenuation * scattering_pdf * sample_color) / pdf_value;
-        color_from_emission + color_from_scatter
+        let (core, tile_margins) = tile_film.split_margins(tile.x0, tile.y0, tile.x1, tile.y1);
+        (core, tile_margins, tile_samples)
     }
     pub fn render(
         &self,
@@ -135,25 +391,64 @@ impl Camera {
         path: &std::path::Path,
     ) {
         let mut img: RgbImage = ImageBuffer::new(self.image_width, self.image_height);
-        let pixels: Vec<(u32, u32, Color)> = (0..self.image_height)
-            .into_par_iter()
-            .flat_map(|j| {
-                (0..self.image_width).into_par_iter().map(move |i| {
-                    let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                    for s_j in 0..self.sqrt_spp {
-                        for s_i in 0..self.sqrt_spp {
-                            let r = self.get_ray(i, j, s_i, s_j);
-                            pixel_color =
-                                pixel_color + self.ray_color(&r, self.max_depth, world, lights);
-                        }
-                    }
-                    (i, j, pixel_color)
-                })
+        let filter = Filter::new(self.filter_type, self.filter_radius);
+        println!(
+            "Rendering with {} samples per pixel ({} sampler, {} filter of radius {})",
+            self.samples_per_pixel, self.sampler_type, self.filter_type, filter.radius
+        );
+        let min_samples = self.adaptive_min_samples();
+        if self.adaptive_threshold > 0.0 && self.samples_per_pixel <= ADAPTIVE_MIN_SAMPLES {
+            if min_samples >= self.samples_per_pixel {
+                eprintln!(
+                    "Warning: adaptive sampling has no effect with {} samples per pixel; it needs more than {}.",
+                    self.samples_per_pixel, min_samples
+                );
+            } else {
+                eprintln!(
+                    "Warning: {} samples per pixel is below the adaptive minimum of {}; pixels may stop after {} samples.",
+                    self.samples_per_pixel, ADAPTIVE_MIN_SAMPLES, min_samples
+                );
+            }
+        }
+        let film = Mutex::new(Film::new(0, 0, self.image_width, self.image_height));
+        let progress = ProgressBar::new((self.image_width * self.image_height) as u64);
+        progress.set_style(
+            ProgressStyle::with_template(
+                "{spinner} [{elapsed_precise}] [{wide_bar}] {percent}% ETA {eta} {msg}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        let traced_samples = AtomicU64::new(0);
+        // 每个块渲染到外扩滤波器半径的局部胶片中. 完成后内部区域直接写入整张胶片,
+        // 外扩条带在全部块完成后按块序号累加, 使非盒式滤波器的结果与线程调度无关
+        let tiles = spiral_tiles(self.image_width, self.image_height);
+        // par_bridge 按迭代顺序逐个分发, 空闲线程总是领取螺旋序中的下一个块
+        let mut margins: Vec<(usize, Vec<Film>)> = tiles
+            .iter()
+            .enumerate()
+            .par_bridge()
+            .map(|(index, tile)| {
+                let (core, tile_margins, tile_samples) =
+                    self.render_tile(tile, &filter, min_samples, world, lights);
+                film.lock().unwrap().merge(&core);
+                let traced =
+                    traced_samples.fetch_add(tile_samples, Ordering::Relaxed) + tile_samples;
+                let seconds = progress.elapsed().as_secs_f64().max(1e-3);
+                progress.set_message(format!("{:.2} Msamples/s", traced as f64 / seconds / 1e6));
+                progress.inc(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as u64);
+                (index, tile_margins)
             })
             .collect();
-        for (i, j, pixel_color) in pixels {
-            write_color(i, j, &(pixel_color * self.pixel_sample_scale), &mut img);
+        progress.finish();
+        let mut film = film.into_inner().unwrap();
+        margins.sort_unstable_by_key(|(index, _)| *index);
+        for (_, tile_margins) in &margins {
+            for strip in tile_margins {
+                film.merge(strip);
+            }
         }
+        film.write_image(&mut img);
         let prefix = path.parent().unwrap();
         std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
         println!("Saving image to: {:?}", path);
@@ -166,5 +461,235 @@ impl Camera {
         if let Err(e) = img.save(path) {
             eprintln!("Failed to save image: {}", e);
         }
+        if self.adaptive_threshold > 0.0 {
+            let mut heat: RgbImage = ImageBuffer::new(self.image_width, self.image_height);
+            film.write_heat_map(self.samples_per_pixel, &mut heat);
+            println!(
+                "Adaptive sampling used {:.1} samples per pixel on average (at most {})",
+                film.total_samples() as f64 / (self.image_width * self.image_height) as f64,
+                self.samples_per_pixel
+            );
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            let heat_path = path.with_file_name(format!("{}_samples.png", stem));
+            println!("Saving sample heat map to: {:?}", heat_path);
+            if let Err(e) = heat.save(&heat_path) {
+                eprintln!("Failed to save sample heat map: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::material::{Dielectric, Lambertian, Subsurface};
+    use crate::medium::Medium;
+    use crate::quad::{Quad, make_box};
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn white_subsurface_slab_conserves_energy() {
+        // 白色反照率且无吸收: 每条路径最终都离开平板看到白色背景, 被截断的路径才会变暗
+        let slab = Subsurface::new(Color::new(1.0, 1.0, 1.0), Color::new(0.02, 0.02, 0.02), 1.3);
+        let world: Arc<dyn Hittable> = make_box(
+            &Vec3::new(-50.0, -1.0, -50.0),
+            &Vec3::new(50.0, 0.0, 50.0),
+            Arc::new(slab),
+        );
+        let lights: Arc<dyn Hittable> = Arc::new(HittableList::new());
+        let cam = Camera {
+            max_depth: 50,
+            background: Color::new(1.0, 1.0, 1.0),
+            ..Camera::default()
+        };
+        let mut sampler = IndependentSampler::new(5);
+        let paths = 400;
+        let mut total = 0.0;
+        for index in 0..paths {
+            sampler.start_pixel_sample(0, 0, index);
+            let r = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.3, -1.0, 0.1));
+            total += luminance(&cam.ray_color(
+                &r,
+                cam.max_depth,
+                &world,
+                &lights,
+                &MediumStack::new(),
+                &mut sampler,
+            ));
+        }
+        let mean = total / paths as f64;
+        assert!((mean - 1.0).abs() < 1e-3, "mean radiance {mean}");
+    }
+
+    // 高优先级玻璃内部排列 count 层低优先级的水面, 全部是假命中
+    fn radiance_through_false_hits(count: i32) -> Color {
+        let glass = Medium::new(1.0, Color::new(0.0, 0.0, 0.0), 2);
+        let water = Arc::new(Dielectric::new_absorbing(
+            1.33,
+            Color::new(0.0, 0.0, 0.0),
+            1,
+        ));
+        let mut list = HittableList::new();
+        for k in 1..=count {
+            list.add(Arc::new(Quad::new(
+                &Vec3::new(-1.0, -1.0, -k as f64),
+                &Vec3::new(2.0, 0.0, 0.0),
+                &Vec3::new(0.0, 2.0, 0.0),
+                water.clone(),
+            )));
+        }
+        let world: Arc<dyn Hittable> = Arc::new(list);
+        let lights: Arc<dyn Hittable> = Arc::new(HittableList::new());
+        let cam = Camera {
+            max_depth: 4,
+            background: Color::new(1.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        let mut media = MediumStack::new();
+        media.cross(&glass, true);
+        let r = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut sampler = IndependentSampler::new(0);
+        cam.ray_color(&r, cam.max_depth, &world, &lights, &media, &mut sampler)
+    }
+
+    #[test]
+    fn false_hits_do_not_use_depth_but_are_bounded() {
+        // 假命中不消耗 depth: 远多于 max_depth 层仍能看到背景
+        assert_eq!(radiance_through_false_hits(10), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            radiance_through_false_hits(MAX_FALSE_HIT_CROSSINGS as i32),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        // 超过上限时放弃该路径
+        assert_eq!(
+            radiance_through_false_hits(MAX_FALSE_HIT_CROSSINGS as i32 + 1),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "mean free path")]
+    fn non_positive_mean_free_path_is_rejected() {
+        Subsurface::new(Color::new(1.0, 1.0, 1.0), Color::new(1.0, 0.0, 1.0), 1.3);
+    }
+
+    #[test]
+    fn relative_error_is_the_standard_error_in_gamma_space() {
+        // 0 和 1 交替: 均值 0.5, 无偏方差 0.25 n / (n - 1)
+        let n = 100;
+        let error = relative_error(50.0, 50.0, n);
+        let variance = 0.25 * n as f64 / (n - 1) as f64;
+        let expected = (variance / n as f64).sqrt() / (2.0 * 0.5f64.sqrt());
+        assert!((error - expected).abs() < 1e-12);
+        // 暗像素用亮度下限代替均值, 不会因为均值趋近 0 而永远不收敛
+        let dark = relative_error(0.0, 0.0, n);
+        assert_eq!(dark, 0.0);
+    }
+
+    #[test]
+    fn adaptive_stop_counts_only_finite_samples() {
+        let mut stats = PixelStats::default();
+        for _ in 0..ADAPTIVE_MIN_SAMPLES - 1 {
+            stats.add(0.5);
+        }
+        // 零方差也要等到最小样本数; 第 64 个样本若是 NaN 则不计入, 仍不停止
+        assert!(!stats.converged(ADAPTIVE_MIN_SAMPLES, 1e-3));
+        stats.add(0.5);
+        assert!(stats.converged(ADAPTIVE_MIN_SAMPLES, 1e-3));
+        // 高方差时不停止
+        let mut noisy = PixelStats::default();
+        for i in 0..ADAPTIVE_MIN_SAMPLES {
+            noisy.add((i % 2) as f64);
+        }
+        assert!(!noisy.converged(ADAPTIVE_MIN_SAMPLES, 0.01));
+        assert!(!PixelStats::default().converged(0, 1.0));
+    }
+
+    #[test]
+    fn adaptive_minimum_scales_with_the_budget() {
+        let min_samples = |samples_per_pixel| {
+            Camera {
+                samples_per_pixel,
+                ..Default::default()
+            }
+            .adaptive_min_samples()
+        };
+        assert_eq!(min_samples(1024), ADAPTIVE_MIN_SAMPLES);
+        assert_eq!(min_samples(64), 32);
+        // 预算只有一批时无法提前停止
+        assert_eq!(min_samples(8), ADAPTIVE_BATCH_SIZE);
+    }
+
+    #[test]
+    fn spiral_tiles_cover_every_pixel_once() {
+        for (width, height) in [(1, 1), (31, 33), (70, 45), (100, 64), (257, 129)] {
+            let mut coverage = vec![0; (width * height) as usize];
+            for tile in spiral_tiles(width, height) {
+                assert!(tile.x0 < tile.x1 && tile.y0 < tile.y1);
+                for j in tile.y0..tile.y1 {
+                    for i in tile.x0..tile.x1 {
+                        coverage[(j * width + i) as usize] += 1;
+                    }
+                }
+            }
+            assert!(
+                coverage.iter().all(|&count| count == 1),
+                "{}x{}",
+                width,
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn lanczos_film_is_independent_of_tile_order() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.2)));
+        let mut list = HittableList::new();
+        list.add(Arc::new(Sphere::new(Vec3::new(0.0, 0.0, -3.0), 1.0, mat)));
+        let world: Arc<dyn Hittable> = Arc::new(list);
+        let lights: Arc<dyn Hittable> = Arc::new(HittableList::new());
+        let mut cam = Camera {
+            image_width: 70,
+            aspect_ratio: 70.0 / 45.0,
+            samples_per_pixel: 4,
+            max_depth: 4,
+            background: Color::new(0.8, 0.9, 1.0),
+            filter_type: FilterType::Lanczos,
+            ..Default::default()
+        };
+        cam.initialize();
+        let filter = Filter::new(cam.filter_type, cam.filter_radius);
+        let tiles = spiral_tiles(cam.image_width, cam.image_height);
+        assert!(tiles.len() > 4);
+        let rendered: Vec<(Film, Vec<Film>, u64)> = tiles
+            .iter()
+            .map(|tile| cam.render_tile(tile, &filter, ADAPTIVE_MIN_SAMPLES, &world, &lights))
+            .collect();
+        // 与 render 相同: 内部区域按完成顺序写入, 条带按块序号累加
+        let assemble = |completion: &mut dyn Iterator<Item = usize>| {
+            let mut film = Film::new(0, 0, cam.image_width, cam.image_height);
+            for index in completion {
+                film.merge(&rendered[index].0);
+            }
+            for (_, margins, _) in &rendered {
+                for strip in margins {
+                    film.merge(strip);
+                }
+            }
+            film
+        };
+        let forward = assemble(&mut (0..tiles.len()));
+        let reversed = assemble(&mut (0..tiles.len()).rev());
+        let interleaved = assemble(
+            &mut (0..tiles.len())
+                .step_by(2)
+                .chain((1..tiles.len()).step_by(2)),
+        );
+        assert!(forward == reversed);
+        assert!(forward == interleaved);
+        // 重新渲染同一块得到完全相同的胶片, 与之前渲染过哪些块无关
+        let again = cam.render_tile(&tiles[3], &filter, ADAPTIVE_MIN_SAMPLES, &world, &lights);
+        assert!(again.0 == rendered[3].0);
     }
 }
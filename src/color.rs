@@ -33,3 +33,22 @@ pub fn write_color(x: u32, y: u32, pixel_color: &Color, img: &mut RgbImage) {
     let b = (INTENSITY.clamp(linear_to_gamma(_b)) * 256.0) as u8;
     *pixel = image::Rgb([r, g, b]);
 }
+pub fn srgb_to_linear(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+// 黑 -> 红 -> 黄 -> 白
+pub fn write_heat(x: u32, y: u32, t: f64, img: &mut RgbImage) {
+    let unit = Interval::new(0.0, 1.0);
+    let r = unit.clamp(3.0 * t);
+    let g = unit.clamp(3.0 * t - 1.0);
+    let b = unit.clamp(3.0 * t - 2.0);
+    *img.get_pixel_mut(x, y) =
+        image::Rgb([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]);
+}
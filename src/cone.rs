@@ -0,0 +1,120 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utility::{PI, solve_quadratic};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Cone {
+    center: Vec3,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+impl Cone {
+    pub fn new(
+        center: Vec3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let rvec = Vec3::new(radius, 0.0, radius);
+        Self {
+            center,
+            radius,
+            height,
+            capped,
+            mat,
+            bbox: Aabb::new_points(
+                &(center - rvec),
+                &(center + rvec + Vec3::new(0.0, height, 0.0)),
+            ),
+        }
+    }
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Vec3, f64, f64)> {
+        let o = r.origin - self.center;
+        let d = r.direction;
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let hy = self.height - o.y;
+        let mut closest = ray_t.max;
+        let mut found = None;
+        if let Some((t0, t1)) = solve_quadratic(
+            d.x * d.x + d.z * d.z - k2 * d.y * d.y,
+            2.0 * (o.x * d.x + o.z * d.z + k2 * hy * d.y),
+            o.x * o.x + o.z * o.z - k2 * hy * hy,
+        ) {
+            for t in [t0, t1] {
+                if !ray_t.surrounds(t) || t >= closest {
+                    continue;
+                }
+                let p = o + d * t;
+                if p.y < 0.0 || p.y > self.height {
+                    continue;
+                }
+                let phi = (-p.z).atan2(p.x) + PI;
+                closest = t;
+                found = Some((
+                    t,
+                    Vec3::new(p.x, k2 * (self.height - p.y), p.z).unit(),
+                    phi / (2.0 * PI),
+                    p.y / self.height,
+                ));
+                break;
+            }
+        }
+        if self.capped && d.y.abs() > 1e-12 {
+            let t = -o.y / d.y;
+            if ray_t.surrounds(t) && t < closest {
+                let p = o + d * t;
+                let dist = (p.x * p.x + p.z * p.z).sqrt();
+                if dist <= self.radius {
+                    let phi = (-p.z).atan2(p.x) + PI;
+                    found = Some((
+                        t,
+                        Vec3::new(0.0, -1.0, 0.0),
+                        phi / (2.0 * PI),
+                        dist / self.radius,
+                    ));
+                }
+            }
+        }
+        found
+    }
+}
+impl Hittable for Cone {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, outward_normal, u, v)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, outward_normal, u, v);
+        if !rec.mat.alpha_test(u, v, sampler) {
+            return false;
+        }
+        true
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t)
+            .is_some_and(|(_, _, u, v)| self.mat.alpha_test(u, v, sampler))
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -4,7 +4,8 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::interval::{INTERVAL_UNIVERSE, Interval};
 use crate::material::{Isotropic, Material};
 use crate::ray::Ray;
-use crate::utility::{INFINITY, random_double};
+use crate::sampler::Sampler;
+use crate::utility::INFINITY;
 use crate::vec3::Vec3;
 use std::sync::Arc;
 
@@ -30,16 +31,24 @@ impl ConstantMedium {
     }
 }
 impl Hittable for ConstantMedium {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
         let mut rec1 = HitRecord::default();
         let mut rec2 = HitRecord::default();
-        if !self.boundary.hit(r, &INTERVAL_UNIVERSE, &mut rec1) {
+        if !self.boundary.hit(r, &INTERVAL_UNIVERSE, &mut rec1, sampler) {
             return false;
         }
-        if !self
-            .boundary
-            .hit(r, &Interval::new(rec1.t + 0.0001, INFINITY), &mut rec2)
-        {
+        if !self.boundary.hit(
+            r,
+            &Interval::new(rec1.t + 0.0001, INFINITY),
+            &mut rec2,
+            sampler,
+        ) {
             return false;
         }
         if rec1.t < ray_t.min {
@@ -56,14 +65,16 @@ impl Hittable for ConstantMedium {
         }
         let ray_length = r.direction.length();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * random_double().ln();
+        let hit_distance = self.neg_inv_density * sampler.get_1d().ln();
         if hit_distance > distance_inside_boundary {
             return false;
         }
         rec.t = rec1.t + hit_distance / ray_length;
         rec.p = r.at(rec.t);
         rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.geometric_normal = rec.normal;
         rec.front_face = true;
+        // 相函数描述的是体积散射, 外层实例的表面覆盖材质不适用, 因此不经过 set_material
         rec.mat = self.phase_function.clone();
         true
     }
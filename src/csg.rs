@@ -0,0 +1,175 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::{INTERVAL_UNIVERSE, Interval};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use std::sync::Arc;
+
+#[derive(Clone, Copy)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+impl CsgOp {
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+pub struct Csg {
+    a: Arc<dyn Hittable>,
+    b: Arc<dyn Hittable>,
+    op: CsgOp,
+    bbox: Aabb,
+}
+impl Csg {
+    pub fn new(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>, op: CsgOp) -> Self {
+        let bbox = match op {
+            CsgOp::Union => Aabb::new_aabb(&a.bounding_box(), &b.bounding_box()),
+            CsgOp::Intersection => a.bounding_box().intersect(&b.bounding_box()),
+            CsgOp::Difference => a.bounding_box(),
+        };
+        Self { a, b, op, bbox }
+    }
+}
+impl Hittable for Csg {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let mut hits = Vec::new();
+        self.hit_all(r, ray_t, rec, &mut hits, sampler);
+        match hits.into_iter().next() {
+            Some(first) => {
+                *rec = first;
+                true
+            }
+            None => false,
+        }
+    }
+    fn hit_all(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        outer: &HitRecord,
+        hits: &mut Vec<HitRecord>,
+        sampler: &mut dyn Sampler,
+    ) {
+        if !self.bbox.hit(r, ray_t) {
+            return;
+        }
+        let mut hits_a = Vec::new();
+        let mut hits_b = Vec::new();
+        self.a
+            .hit_all(r, &INTERVAL_UNIVERSE, outer, &mut hits_a, sampler);
+        self.b
+            .hit_all(r, &INTERVAL_UNIVERSE, outer, &mut hits_b, sampler);
+        let mut in_a = hits_a.first().is_some_and(|rec| !rec.front_face);
+        let mut in_b = hits_b.first().is_some_and(|rec| !rec.front_face);
+        let mut events: Vec<(HitRecord, bool)> = hits_a
+            .into_iter()
+            .map(|rec| (rec, true))
+            .chain(hits_b.into_iter().map(|rec| (rec, false)))
+            .collect();
+        events.sort_by(|x, y| x.0.t.partial_cmp(&y.0.t).unwrap());
+        let mut inside = self.op.inside(in_a, in_b);
+        for (mut rec, from_a) in events {
+            if from_a {
+                in_a = rec.front_face;
+            } else {
+                in_b = rec.front_face;
+            }
+            let now_inside = self.op.inside(in_a, in_b);
+            if now_inside != inside && ray_t.surrounds(rec.t) {
+                rec.front_face = now_inside;
+                hits.push(rec);
+            }
+            inside = now_inside;
+        }
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::utility::INFINITY;
+    use crate::vec3::Vec3;
+
+    fn sphere(x: f64) -> Arc<dyn Hittable> {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Vec3::new(x, 0.0, 0.0), 1.0, mat))
+    }
+
+    // 返回沿光线的 (t, 是否进入) 序列
+    fn events(op: CsgOp, origin_x: f64) -> Vec<(f64, bool)> {
+        let csg = Csg::new(sphere(-0.5), sphere(0.5), op);
+        let r = Ray::new(Vec3::new(origin_x, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut hits = Vec::new();
+        let mut sampler = IndependentSampler::new(0);
+        csg.hit_all(
+            &r,
+            &Interval::new(0.001, INFINITY),
+            &HitRecord::default(),
+            &mut hits,
+            &mut sampler,
+        );
+        hits.iter().map(|rec| (rec.t, rec.front_face)).collect()
+    }
+
+    fn assert_events(actual: Vec<(f64, bool)>, expected: &[(f64, bool)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for ((t, entering), (want_t, want_entering)) in actual.iter().zip(expected) {
+            assert!((t - want_t).abs() < 1e-9, "{actual:?}");
+            assert_eq!(entering, want_entering, "{actual:?}");
+        }
+    }
+
+    #[test]
+    fn intervals_from_outside() {
+        // A = [-1.5, 0.5], B = [-0.5, 1.5], 光线从 x = -5 出发
+        assert_events(events(CsgOp::Union, -5.0), &[(3.5, true), (6.5, false)]);
+        assert_events(
+            events(CsgOp::Intersection, -5.0),
+            &[(4.5, true), (5.5, false)],
+        );
+        assert_events(
+            events(CsgOp::Difference, -5.0),
+            &[(3.5, true), (4.5, false)],
+        );
+    }
+
+    #[test]
+    fn intervals_from_inside() {
+        // 光线从两球的重叠区内出发
+        assert_events(events(CsgOp::Union, 0.0), &[(1.5, false)]);
+        assert_events(events(CsgOp::Intersection, 0.0), &[(0.5, false)]);
+        assert_events(events(CsgOp::Difference, 0.0), &[]);
+        // 从 A - B 内部出发
+        assert_events(events(CsgOp::Difference, -1.0), &[(0.5, false)]);
+    }
+
+    #[test]
+    fn disjoint_intersection_is_empty() {
+        let csg = Csg::new(sphere(-3.0), sphere(3.0), CsgOp::Intersection);
+        assert!(csg.bounding_box().is_empty());
+        let r = Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(!csg.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+    }
+}
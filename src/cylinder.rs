@@ -0,0 +1,162 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utility::{INFINITY, PI, solve_quadratic};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Cylinder {
+    center: Vec3,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    side_area: f64,
+    cap_area: f64,
+}
+impl Cylinder {
+    pub fn new(
+        center: Vec3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let rvec = Vec3::new(radius, 0.0, radius);
+        Self {
+            center,
+            radius,
+            height,
+            capped,
+            mat,
+            bbox: Aabb::new_points(
+                &(center - rvec),
+                &(center + rvec + Vec3::new(0.0, height, 0.0)),
+            ),
+            side_area: 2.0 * PI * radius * height,
+            cap_area: if capped { PI * radius * radius } else { 0.0 },
+        }
+    }
+    fn area(&self) -> f64 {
+        self.side_area + 2.0 * self.cap_area
+    }
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Vec3, f64, f64)> {
+        let o = r.origin - self.center;
+        let d = r.direction;
+        let mut closest = ray_t.max;
+        let mut found = None;
+        if let Some((t0, t1)) = solve_quadratic(
+            d.x * d.x + d.z * d.z,
+            2.0 * (o.x * d.x + o.z * d.z),
+            o.x * o.x + o.z * o.z - self.radius * self.radius,
+        ) {
+            for t in [t0, t1] {
+                if !ray_t.surrounds(t) || t >= closest {
+                    continue;
+                }
+                let p = o + d * t;
+                if p.y < 0.0 || p.y > self.height {
+                    continue;
+                }
+                let phi = (-p.z).atan2(p.x) + PI;
+                closest = t;
+                found = Some((
+                    t,
+                    Vec3::new(p.x, 0.0, p.z) / self.radius,
+                    phi / (2.0 * PI),
+                    p.y / self.height,
+                ));
+                break;
+            }
+        }
+        if self.capped && d.y.abs() > 1e-12 {
+            for (y, ny) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (y - o.y) / d.y;
+                if !ray_t.surrounds(t) || t >= closest {
+                    continue;
+                }
+                let p = o + d * t;
+                let dist = (p.x * p.x + p.z * p.z).sqrt();
+                if dist > self.radius {
+                    continue;
+                }
+                let phi = (-p.z).atan2(p.x) + PI;
+                closest = t;
+                found = Some((
+                    t,
+                    Vec3::new(0.0, ny, 0.0),
+                    phi / (2.0 * PI),
+                    dist / self.radius,
+                ));
+            }
+        }
+        found
+    }
+}
+impl Hittable for Cylinder {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, outward_normal, u, v)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, outward_normal, u, v);
+        if !rec.mat.alpha_test(u, v, sampler) {
+            return false;
+        }
+        true
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t)
+            .is_some_and(|(_, _, u, v)| self.mat.alpha_test(u, v, sampler))
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        let ray = Ray::new(*origin, *direction);
+        let mut rec = HitRecord::default();
+        let mut t_min = 0.001;
+        let mut pdf = 0.0;
+        while self.hit(&ray, &Interval::new(t_min, INFINITY), &mut rec, sampler) {
+            let distance_squared = rec.t * rec.t * direction.squared_length();
+            let cosine = (direction.dot(&rec.geometric_normal) / direction.length()).abs();
+            pdf += distance_squared / (cosine * self.area());
+            t_min = rec.t + 0.001;
+        }
+        pdf
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let phi = 2.0 * PI * sampler.get_1d();
+        let pick = sampler.get_1d() * self.area();
+        let p = if pick < self.side_area {
+            Vec3::new(
+                self.radius * phi.cos(),
+                self.height * sampler.get_1d(),
+                self.radius * phi.sin(),
+            )
+        } else {
+            let r = self.radius * sampler.get_1d().sqrt();
+            let y = if pick < self.side_area + self.cap_area {
+                0.0
+            } else {
+                self.height
+            };
+            Vec3::new(r * phi.cos(), y, r * phi.sin())
+        };
+        self.center + p - *origin
+    }
+}
@@ -0,0 +1,113 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utility::{INFINITY, PI};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Disk {
+    center: Vec3,
+    radius: f64,
+    uvw: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let uvw = Onb::new(&normal);
+        let normal = uvw.w();
+        let extent = Vec3::new(
+            radius * (1.0 - normal.x * normal.x).max(0.0).sqrt(),
+            radius * (1.0 - normal.y * normal.y).max(0.0).sqrt(),
+            radius * (1.0 - normal.z * normal.z).max(0.0).sqrt(),
+        );
+        Self {
+            center,
+            radius,
+            uvw,
+            mat,
+            bbox: Aabb::new_points(&(center - extent), &(center + extent)),
+            normal,
+            d: normal.dot(&center),
+            area: PI * radius * radius,
+        }
+    }
+}
+impl Disk {
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64, f64)> {
+        let denom = self.normal.dot(&r.direction);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = (self.d - self.normal.dot(&r.origin)) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+        let local = r.at(t) - self.center;
+        let x = local.dot(&self.uvw.u());
+        let y = local.dot(&self.uvw.v());
+        let dist = (x * x + y * y).sqrt();
+        if dist > self.radius {
+            return None;
+        }
+        Some((t, (y.atan2(x) + PI) / (2.0 * PI), dist / self.radius))
+    }
+}
+impl Hittable for Disk {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, u, v)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, self.normal, rec.u, rec.v);
+        if !rec.mat.alpha_test(rec.u, rec.v, sampler) {
+            return false;
+        }
+        true
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t)
+            .is_some_and(|(_, u, v)| self.mat.alpha_test(u, v, sampler))
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(
+            &Ray::new(*origin, *direction),
+            &Interval::new(0.001, INFINITY),
+            &mut rec,
+            sampler,
+        ) {
+            return 0.0;
+        }
+        let distance_squared = rec.t * rec.t * direction.squared_length();
+        let cosine = (direction.dot(&rec.geometric_normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let r = self.radius * r1.sqrt();
+        let phi = 2.0 * PI * r2;
+        let p = self.center + self.uvw.u() * (r * phi.cos()) + self.uvw.v() * (r * phi.sin());
+        p - *origin
+    }
+}
@@ -0,0 +1,277 @@
+use crate::color::{Color, write_color, write_heat};
+use crate::filter::Filter;
+use image::RgbImage;
+
+// 负瓣滤波器的权重和可能接近 0 甚至为负, 归一化时至少除以绝对权重和的这一比例
+const MIN_WEIGHT_FRACTION: f64 = 0.1;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct FilmPixel {
+    color_sum: Color,
+    weight_sum: f64,
+    abs_weight_sum: f64,
+    samples: u32,
+}
+
+// 覆盖图像中 [x0, x0 + width) x [y0, y0 + height) 的一块区域
+#[derive(PartialEq)]
+pub struct Film {
+    x0: u32,
+    y0: u32,
+    width: u32,
+    height: u32,
+    pixels: Vec<FilmPixel>,
+}
+impl Film {
+    pub fn new(x0: u32, y0: u32, width: u32, height: u32) -> Self {
+        Self {
+            x0,
+            y0,
+            width,
+            height,
+            pixels: vec![FilmPixel::default(); (width * height) as usize],
+        }
+    }
+    fn pixel_mut(&mut self, i: u32, j: u32) -> &mut FilmPixel {
+        &mut self.pixels[((j - self.y0) * self.width + (i - self.x0)) as usize]
+    }
+    // 将 (x, y) 处的样本按滤波器权重溅射到半径内的所有像素中心
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Color, filter: &Filter) {
+        let x_min = ((x - filter.radius).ceil().max(self.x0 as f64)) as u32;
+        let y_min = ((y - filter.radius).ceil().max(self.y0 as f64)) as u32;
+        let x_max = (x + filter.radius)
+            .floor()
+            .min((self.x0 + self.width - 1) as f64);
+        let y_max = (y + filter.radius)
+            .floor()
+            .min((self.y0 + self.height - 1) as f64);
+        if x_max < 0.0 || y_max < 0.0 {
+            return;
+        }
+        for j in y_min..=y_max as u32 {
+            for i in x_min..=x_max as u32 {
+                let weight = filter.evaluate(i as f64 - x, j as f64 - y);
+                if weight == 0.0 {
+                    continue;
+                }
+                let pixel = self.pixel_mut(i, j);
+                pixel.color_sum = pixel.color_sum + *color * weight;
+                pixel.weight_sum += weight;
+                pixel.abs_weight_sum += weight.abs();
+            }
+        }
+    }
+    pub fn add_sample_count(&mut self, i: u32, j: u32, samples: u32) {
+        self.pixel_mut(i, j).samples += samples;
+    }
+    pub fn merge(&mut self, other: &Film) {
+        for j in 0..other.height {
+            for i in 0..other.width {
+                let src = other.pixels[(j * other.width + i) as usize];
+                let dst = self.pixel_mut(other.x0 + i, other.y0 + j);
+                dst.color_sum = dst.color_sum + src.color_sum;
+                dst.weight_sum += src.weight_sum;
+                dst.abs_weight_sum += src.abs_weight_sum;
+                dst.samples += src.samples;
+            }
+        }
+    }
+    // 复制 [x0, x1) x [y0, y1) 与本胶片的重叠部分, 无重叠时返回 None
+    fn crop(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> Option<Film> {
+        let x0 = x0.max(self.x0);
+        let y0 = y0.max(self.y0);
+        let x1 = x1.min(self.x0 + self.width);
+        let y1 = y1.min(self.y0 + self.height);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let mut film = Film::new(x0, y0, x1 - x0, y1 - y0);
+        for j in y0..y1 {
+            let start = ((j - self.y0) * self.width + (x0 - self.x0)) as usize;
+            let row = &self.pixels[start..start + (x1 - x0) as usize];
+            let offset = ((j - y0) * film.width) as usize;
+            film.pixels[offset..offset + row.len()].copy_from_slice(row);
+        }
+        Some(film)
+    }
+    // 拆成内部区域 [x0, x1) x [y0, y1) 与四周的外扩条带.
+    // 各块的内部区域互不重叠, 可按任意完成顺序写入; 条带需按固定顺序累加, 结果才与调度无关
+    pub fn split_margins(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> (Film, Vec<Film>) {
+        let core = self.crop(x0, y0, x1, y1).unwrap();
+        let (fx1, fy1) = (self.x0 + self.width, self.y0 + self.height);
+        let margins = [
+            self.crop(self.x0, self.y0, fx1, y0),
+            self.crop(self.x0, y1, fx1, fy1),
+            self.crop(self.x0, y0, x0, y1),
+            self.crop(x1, y0, fx1, y1),
+        ];
+        (core, margins.into_iter().flatten().collect())
+    }
+    pub fn total_samples(&self) -> u64 {
+        self.pixels.iter().map(|p| p.samples as u64).sum()
+    }
+    fn resolve(&self, i: u32, j: u32) -> Color {
+        let pixel = &self.pixels[(j * self.width + i) as usize];
+        let weight_sum = pixel
+            .weight_sum
+            .max(MIN_WEIGHT_FRACTION * pixel.abs_weight_sum);
+        if weight_sum > 0.0 {
+            pixel.color_sum / weight_sum
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+    pub fn write_image(&self, img: &mut RgbImage) {
+        for j in 0..self.height {
+            for i in 0..self.width {
+                write_color(self.x0 + i, self.y0 + j, &self.resolve(i, j), img);
+            }
+        }
+    }
+    pub fn write_heat_map(&self, max_samples: u32, img: &mut RgbImage) {
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let samples = self.pixels[(j * self.width + i) as usize].samples;
+                write_heat(
+                    self.x0 + i,
+                    self.y0 + j,
+                    samples as f64 / max_samples as f64,
+                    img,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterType;
+    use crate::sampler::{IndependentSampler, Sampler};
+
+    const FILTER_TYPES: [FilterType; 5] = [
+        FilterType::Box,
+        FilterType::Tent,
+        FilterType::Gaussian,
+        FilterType::Mitchell,
+        FilterType::Lanczos,
+    ];
+
+    #[test]
+    fn constant_radiance_is_preserved_by_every_filter() {
+        let color = Color::new(0.25, 0.5, 1.0);
+        let mut sampler = IndependentSampler::new(9);
+        for filter_type in FILTER_TYPES {
+            let filter = Filter::new(filter_type, 0.0);
+            let mut film = Film::new(0, 0, 16, 16);
+            for _ in 0..20000 {
+                let (x, y) = sampler.get_2d();
+                film.add_sample(x * 16.0, y * 16.0, &color, &filter);
+            }
+            // 只检查离边界超过滤波器半径的像素
+            let margin = filter.radius.ceil() as u32;
+            for j in margin..16 - margin {
+                for i in margin..16 - margin {
+                    let resolved = film.resolve(i, j);
+                    assert!(
+                        (resolved - color).length() < 1e-9,
+                        "{filter_type} at ({i}, {j})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn near_zero_weight_sums_do_not_blow_up() {
+        let mut film = Film::new(0, 0, 2, 1);
+        // 正负权重几乎抵消, 或完全为负
+        film.pixels[0] = FilmPixel {
+            color_sum: Color::new(0.5, 0.5, 0.5),
+            weight_sum: 1e-12,
+            abs_weight_sum: 2.0,
+            samples: 2,
+        };
+        film.pixels[1] = FilmPixel {
+            color_sum: Color::new(-0.5, -0.5, -0.5),
+            weight_sum: -0.5,
+            abs_weight_sum: 0.5,
+            samples: 1,
+        };
+        let resolved = film.resolve(0, 0);
+        assert!((resolved - Color::new(2.5, 2.5, 2.5)).length() < 1e-9);
+        let resolved = film.resolve(1, 0);
+        assert!((resolved - Color::new(-10.0, -10.0, -10.0)).length() < 1e-9);
+    }
+
+    // 模拟分块渲染: 内部区域按完成顺序写入, 条带最后按块序号累加
+    fn assemble(tiles: &[(Film, Vec<Film>)], completion: &[usize]) -> Film {
+        let mut film = Film::new(0, 0, 12, 12);
+        for &index in completion {
+            film.merge(&tiles[index].0);
+        }
+        for (_, margins) in tiles {
+            for strip in margins {
+                film.merge(strip);
+            }
+        }
+        film
+    }
+
+    #[test]
+    fn tile_merge_is_independent_of_completion_order() {
+        let filter = Filter::new(FilterType::Mitchell, 0.0);
+        let margin = filter.radius.ceil() as u32;
+        let mut sampler = IndependentSampler::new(4);
+        let mut tiles = Vec::new();
+        let mut reference = Film::new(0, 0, 12, 12);
+        for ty in 0..3u32 {
+            for tx in 0..3u32 {
+                let (x0, y0) = (tx * 4, ty * 4);
+                let fx0 = x0.saturating_sub(margin);
+                let fy0 = y0.saturating_sub(margin);
+                let fx1 = (x0 + 4 + margin).min(12);
+                let fy1 = (y0 + 4 + margin).min(12);
+                let mut tile_film = Film::new(fx0, fy0, fx1 - fx0, fy1 - fy0);
+                for _ in 0..200 {
+                    let (u, v) = sampler.get_2d();
+                    let color = Color::random(&mut sampler);
+                    tile_film.add_sample(x0 as f64 + 4.0 * u, y0 as f64 + 4.0 * v, &color, &filter);
+                }
+                reference.merge(&tile_film);
+                tiles.push(tile_film.split_margins(x0, y0, x0 + 4, y0 + 4));
+            }
+        }
+        let forward = assemble(&tiles, &[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let shuffled = assemble(&tiles, &[4, 8, 0, 6, 2, 7, 1, 5, 3]);
+        for (a, b) in forward.pixels.iter().zip(&shuffled.pixels) {
+            assert_eq!(a.color_sum.x.to_bits(), b.color_sum.x.to_bits());
+            assert_eq!(a.weight_sum.to_bits(), b.weight_sum.to_bits());
+        }
+        // 内部区域与条带合起来恰好覆盖整个局部胶片
+        for (a, b) in forward.pixels.iter().zip(&reference.pixels) {
+            assert!((a.weight_sum - b.weight_sum).abs() < 1e-9);
+            assert!((a.color_sum - b.color_sum).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn heat_map_scales_sample_counts_to_the_budget() {
+        let mut film = Film::new(0, 0, 4, 1);
+        for (i, samples) in [0, 32, 64, 64].into_iter().enumerate() {
+            film.add_sample_count(i as u32, 0, samples);
+        }
+        // 合并后的块的样本数会累加
+        let mut tile = Film::new(3, 0, 1, 1);
+        tile.add_sample_count(3, 0, 64);
+        film.merge(&tile);
+        assert_eq!(film.total_samples(), 224);
+        let mut img = RgbImage::new(4, 1);
+        film.write_heat_map(64, &mut img);
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(img.get_pixel(1, 0).0, [255, 127, 0]);
+        assert_eq!(img.get_pixel(2, 0).0, [255, 255, 255]);
+        // 超出预算的计数饱和为白色
+        assert_eq!(img.get_pixel(3, 0).0, [255, 255, 255]);
+    }
+}
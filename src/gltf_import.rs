@@ -0,0 +1,605 @@
+use crate::asset::{LoadError, SearchPaths};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::disk::Disk;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::instance::{Instance, build_tlas};
+use crate::mat4::Mat4;
+use crate::material::{
+    Dielectric, DiffuseLight, Glossy, Lambertian, MappedMaterial, Material, SpotLight,
+};
+use crate::rtw_stb_image::RtwImage;
+use crate::sphere::Sphere;
+use crate::texture::{ChannelTexture, ImageTexture, SolidColor, Texture};
+use crate::triangle::TriangleMesh;
+use crate::utility::PI;
+use crate::vec3::Vec3;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::material::AlphaMode;
+use std::sync::Arc;
+
+const LUMENS_PER_WATT: f64 = 683.0;
+const PUNCTUAL_LIGHT_RADIUS: f64 = 0.05;
+const SUN_DISTANCE_SCALE: f64 = 100.0;
+const MIN_SUN_DISTANCE: f64 = 1000.0;
+const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+pub struct GltfScene {
+    pub world: HittableList,
+    pub lights: HittableList,
+    pub camera: Option<Camera>,
+}
+
+struct GltfImage {
+    rgb: Arc<RtwImage>,
+    alpha: Option<Vec<u8>>,
+}
+
+struct GltfLoader<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    materials: Vec<Arc<dyn Material>>,
+    blases: Vec<Option<Arc<dyn Hittable>>>,
+    instances: Vec<Instance>,
+    suns: Vec<(Color, Vec3)>,
+    scene: GltfScene,
+}
+
+// 网格只保存一组纹理坐标, 按基础色贴图的 TEXCOORD 集和 KHR_texture_transform 烘焙进去
+#[derive(Clone, Copy, PartialEq)]
+struct UvMapping {
+    set: u32,
+    transform: [[f64; 3]; 2],
+}
+impl Default for UvMapping {
+    fn default() -> Self {
+        Self {
+            set: 0,
+            transform: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        }
+    }
+}
+impl UvMapping {
+    fn from_info(info: &gltf::texture::Info) -> Self {
+        let Some(transform) = info.texture_transform() else {
+            return Self {
+                set: info.tex_coord(),
+                ..Self::default()
+            };
+        };
+        let [ox, oy] = transform.offset().map(|x| x as f64);
+        let [sx, sy] = transform.scale().map(|x| x as f64);
+        let (sin, cos) = (transform.rotation() as f64).sin_cos();
+        Self {
+            set: transform.tex_coord().unwrap_or(info.tex_coord()),
+            transform: [[cos * sx, sin * sy, ox], [-sin * sx, cos * sy, oy]],
+        }
+    }
+    fn apply(&self, uv: [f32; 2]) -> [f64; 2] {
+        let [u, v] = uv.map(|x| x as f64);
+        let [a, b] = self.transform;
+        [a[0] * u + a[1] * v + a[2], b[0] * u + b[1] * v + b[2]]
+    }
+}
+
+fn uv_mapping(material: &gltf::Material) -> UvMapping {
+    material
+        .pbr_metallic_roughness()
+        .base_color_texture()
+        .map_or_else(UvMapping::default, |info| UvMapping::from_info(&info))
+}
+
+pub fn load_gltf(gltf_filename: &str, search_paths: &SearchPaths) -> Result<GltfScene, LoadError> {
+    let gltf_path = search_paths
+        .resolve(gltf_filename)
+        .ok_or_else(|| LoadError::MissingFile(gltf_filename.to_string()))?;
+    let parse_error = |message: String| LoadError::Parse {
+        path: gltf_path.clone(),
+        message,
+    };
+    let (document, buffers, images) =
+        gltf::import(&gltf_path).map_err(|err| parse_error(err.to_string()))?;
+    let images: Vec<GltfImage> = images.iter().map(convert_image).collect();
+    let mut materials: Vec<Arc<dyn Material>> = document
+        .materials()
+        .map(|material| load_material(&material, &images))
+        .collect();
+    println!("Found {} materials in glTF file.", materials.len());
+    materials.push(Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))));
+    let mut loader = GltfLoader {
+        buffers: &buffers,
+        materials,
+        blases: Vec::new(),
+        instances: Vec::new(),
+        suns: Vec::new(),
+        scene: GltfScene {
+            world: HittableList::new(),
+            lights: HittableList::new(),
+            camera: None,
+        },
+    };
+    let blases = document
+        .meshes()
+        .map(|mesh| loader.load_mesh(&mesh))
+        .collect();
+    loader.blases = blases;
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| parse_error("no scene".to_string()))?;
+    for node in scene.nodes() {
+        loader.visit(&node, Mat4::identity());
+    }
+    let instances = std::mem::take(&mut loader.instances);
+    if !instances.is_empty() {
+        loader.scene.world.add(Arc::new(build_tlas(instances)));
+    }
+    loader.add_suns();
+    println!("done glTF loading!");
+    Ok(loader.scene)
+}
+
+impl GltfLoader<'_> {
+    fn load_mesh(&self, mesh: &gltf::Mesh) -> Option<Arc<dyn Hittable>> {
+        let default_index = (self.materials.len() - 1) as u32;
+        let mut triangle_mesh = TriangleMesh::new();
+        let primitives: Vec<_> = mesh
+            .primitives()
+            .filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles)
+            .collect();
+        let has_texcoords = primitives.iter().any(|primitive| {
+            let set = uv_mapping(&primitive.material()).set;
+            primitive.get(&gltf::Semantic::TexCoords(set)).is_some()
+        });
+        let has_normals = primitives
+            .iter()
+            .all(|primitive| primitive.get(&gltf::Semantic::Normals).is_some());
+        for primitive in &primitives {
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let base = triangle_mesh.positions.len() as u32;
+            for p in positions {
+                triangle_mesh
+                    .positions
+                    .push(Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64));
+            }
+            let vertex_count = triangle_mesh.positions.len() - base as usize;
+            if has_texcoords {
+                let mapping = uv_mapping(&primitive.material());
+                match reader.read_tex_coords(mapping.set) {
+                    Some(texcoords) => {
+                        for t in texcoords.into_f32() {
+                            let [u, v] = mapping.apply(t);
+                            triangle_mesh.texcoords.push([u, 1.0 - v]);
+                        }
+                    }
+                    None => triangle_mesh
+                        .texcoords
+                        .extend(std::iter::repeat_n([0.0, 0.0], vertex_count)),
+                }
+            }
+            if has_normals {
+                if let Some(normals) = reader.read_normals() {
+                    for n in normals {
+                        triangle_mesh.normals.push(Vec3::new(
+                            n[0] as f64,
+                            n[1] as f64,
+                            n[2] as f64,
+                        ));
+                    }
+                }
+            }
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertex_count as u32).collect(),
+            };
+            let material_index = match primitive.material().index() {
+                Some(index) if index < default_index as usize => index as u32,
+                _ => default_index,
+            };
+            for f in indices.chunks_exact(3) {
+                triangle_mesh
+                    .indices
+                    .push([base + f[0], base + f[1], base + f[2]]);
+                triangle_mesh.face_materials.push(material_index);
+            }
+        }
+        if triangle_mesh.face_count() == 0 {
+            return None;
+        }
+        triangle_mesh.materials = self.materials.clone();
+        Some(triangle_mesh.build_bvh())
+    }
+    fn visit(&mut self, node: &gltf::Node, parent: Mat4) {
+        let matrix = parent * to_mat4(node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            if let Some(blas) = &self.blases[mesh.index()] {
+                self.instances.push(Instance::new(blas, matrix));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            if self.scene.camera.is_none() {
+                self.scene.camera = load_camera(&camera, &matrix);
+            }
+        }
+        if let Some(light) = node.light() {
+            self.add_light(&light, &matrix);
+        }
+        for child in node.children() {
+            self.visit(&child, matrix);
+        }
+    }
+    fn add_light(&mut self, light: &gltf::khr_lights_punctual::Light, matrix: &Mat4) {
+        let [r, g, b] = light.color();
+        let color = Color::new(r as f64, g as f64, b as f64);
+        let intensity = light.intensity() as f64 / LUMENS_PER_WATT;
+        let position = matrix.transform_point(&Vec3::new(0.0, 0.0, 0.0));
+        let direction = matrix.transform_vector(&Vec3::new(0.0, 0.0, -1.0)).unit();
+        let radiance = intensity / (PI * PUNCTUAL_LIGHT_RADIUS * PUNCTUAL_LIGHT_RADIUS);
+        let emission: Arc<dyn Material> = match light.kind() {
+            Kind::Point => Arc::new(DiffuseLight::new(&(color * radiance))),
+            Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Arc::new(SpotLight::new(
+                &(color * radiance),
+                direction,
+                inner_cone_angle as f64,
+                outer_cone_angle as f64,
+            )),
+            // 太阳的位置取决于整个场景的范围, 遍历完所有节点后再放置
+            Kind::Directional => {
+                self.suns.push((color * intensity, direction));
+                return;
+            }
+        };
+        if light.range().is_some() {
+            eprintln!(
+                "Warning: glTF light {} has a range cutoff, which is ignored.",
+                light.index()
+            );
+        }
+        let light: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(position, PUNCTUAL_LIGHT_RADIUS, emission));
+        self.scene.world.add(light.clone());
+        self.scene.lights.add(light);
+    }
+    fn add_suns(&mut self) {
+        let bbox = self.scene.world.bounding_box();
+        let (center, extent) = if bbox.is_empty() {
+            (Vec3::default(), 0.0)
+        } else {
+            let diagonal = Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size());
+            (bbox.centroid(), diagonal.length())
+        };
+        let distance = (extent * SUN_DISTANCE_SCALE).max(MIN_SUN_DISTANCE);
+        for (irradiance, direction) in std::mem::take(&mut self.suns) {
+            let radiance = irradiance / (PI * SUN_ANGULAR_RADIUS * SUN_ANGULAR_RADIUS);
+            let light: Arc<dyn Hittable> = Arc::new(Disk::new(
+                center - direction * distance,
+                direction,
+                distance * SUN_ANGULAR_RADIUS,
+                Arc::new(DiffuseLight::new(&radiance)),
+            ));
+            self.scene.world.add(light.clone());
+            self.scene.lights.add(light);
+        }
+    }
+}
+
+fn to_mat4(columns: [[f32; 4]; 4]) -> Mat4 {
+    let mut mat = Mat4::identity();
+    for (i, row) in mat.m.iter_mut().enumerate() {
+        for (j, item) in row.iter_mut().enumerate() {
+            *item = columns[j][i] as f64;
+        }
+    }
+    mat
+}
+
+fn load_camera(camera: &gltf::Camera, matrix: &Mat4) -> Option<Camera> {
+    let gltf::camera::Projection::Perspective(perspective) = camera.projection() else {
+        return None;
+    };
+    let mut cam = Camera::default();
+    cam.aspect_ratio = perspective.aspect_ratio().unwrap_or(16.0 / 9.0) as f64;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.vfov = (perspective.yfov() as f64).to_degrees();
+    cam.lookfrom = matrix.transform_point(&Vec3::new(0.0, 0.0, 0.0));
+    cam.lookat = cam.lookfrom + matrix.transform_vector(&Vec3::new(0.0, 0.0, -1.0));
+    cam.vup = matrix.transform_vector(&Vec3::new(0.0, 1.0, 0.0));
+    cam.defocus_angle = 0.0;
+    cam.focus_dist = 10.0;
+    cam.background = Color::new(0.0, 0.0, 0.0);
+    Some(cam)
+}
+
+fn convert_image(data: &gltf::image::Data) -> GltfImage {
+    let (channels, bytes) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |texel: &[u8], c: usize| -> u8 {
+        let raw = &texel[c * bytes..(c + 1) * bytes];
+        match bytes {
+            1 => raw[0],
+            2 => (u16::from_ne_bytes([raw[0], raw[1]]) >> 8) as u8,
+            _ => {
+                let value = f32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]);
+                (value.clamp(0.0, 1.0) * 255.0) as u8
+            }
+        }
+    };
+    let mut rgb = Vec::with_capacity((data.width * data.height * 3) as usize);
+    let mut alpha = (channels == 4).then(Vec::new);
+    for texel in data.pixels.chunks_exact(channels * bytes) {
+        for c in 0..3 {
+            rgb.push(channel(texel, c.min(channels - 1)));
+        }
+        if let Some(alpha) = &mut alpha {
+            alpha.push(channel(texel, 3));
+        }
+    }
+    GltfImage {
+        rgb: Arc::new(RtwImage::from_rgb(
+            data.width as usize,
+            data.height as usize,
+            rgb,
+        )),
+        alpha,
+    }
+}
+
+fn load_material(material: &gltf::Material, images: &[GltfImage]) -> Arc<dyn Material> {
+    let image = |info: &gltf::texture::Info| &images[info.texture().source().index()];
+    let pbr = material.pbr_metallic_roughness();
+    warn_uv_mismatch(material);
+    warn_dropped_textures(material);
+    let [r, g, b, a] = pbr.base_color_factor();
+    let base_color = Color::new(r as f64, g as f64, b as f64);
+    let albedo: Arc<dyn Texture> = match pbr.base_color_texture() {
+        Some(info) => Arc::new(ImageTexture::from_image(
+            image(&info).rgb.clone(),
+            base_color,
+        )),
+        None => Arc::new(SolidColor::new_color(&base_color)),
+    };
+    let (roughness, metallic): (Arc<dyn Texture>, Arc<dyn Texture>) =
+        match pbr.metallic_roughness_texture() {
+            Some(info) => (
+                Arc::new(ChannelTexture::new(
+                    image(&info).rgb.clone(),
+                    1,
+                    pbr.roughness_factor() as f64,
+                )),
+                Arc::new(ChannelTexture::new(
+                    image(&info).rgb.clone(),
+                    2,
+                    pbr.metallic_factor() as f64,
+                )),
+            ),
+            None => (
+                solid_value(pbr.roughness_factor() as f64),
+                solid_value(pbr.metallic_factor() as f64),
+            ),
+        };
+    let transmission = material
+        .transmission()
+        .map_or(0.0, |transmission| transmission.transmission_factor());
+    let mut base: Arc<dyn Material> = if transmission > 0.5 {
+        Arc::new(Dielectric::new(material.ior().unwrap_or(1.5) as f64))
+    } else {
+        Arc::new(Glossy::new(albedo, solid_value(0.04), roughness, metallic))
+    };
+    let [er, eg, eb] = material.emissive_factor();
+    let emission = Color::new(er as f64, eg as f64, eb as f64)
+        * material.emissive_strength().unwrap_or(1.0) as f64;
+    let emissive_texture = material.emissive_texture();
+    if emissive_texture.is_none() && !emission.near_zero() {
+        base = Arc::new(DiffuseLight::new(&emission));
+    }
+    let alpha = match material.alpha_mode() {
+        AlphaMode::Opaque => None,
+        AlphaMode::Mask => {
+            let cutoff = material.alpha_cutoff().unwrap_or(0.5);
+            Some((Some(cutoff), a))
+        }
+        AlphaMode::Blend => Some((None, a)),
+    };
+    let normal_texture = material.normal_texture();
+    if normal_texture.is_none() && emissive_texture.is_none() && alpha.is_none() {
+        return base;
+    }
+    let mut mapped = MappedMaterial::new(base);
+    if let Some(normal) = normal_texture {
+        mapped.set_normal_image(images[normal.texture().source().index()].rgb.clone());
+        mapped.set_normal_scale(normal.scale() as f64);
+    }
+    if let Some(info) = emissive_texture {
+        let strength = emission.x.max(emission.y).max(emission.z);
+        mapped.set_light_image(image(&info).rgb.clone(), strength);
+    }
+    if let Some((cutoff, factor)) = alpha {
+        let texture = pbr.base_color_texture();
+        match texture.as_ref().map(image) {
+            Some(GltfImage {
+                rgb,
+                alpha: Some(values),
+            }) => {
+                let data = values
+                    .iter()
+                    .flat_map(|&value| {
+                        let value = value as f32 / 255.0 * factor;
+                        let value = match cutoff {
+                            Some(cutoff) if value >= cutoff => 255,
+                            Some(_) => 0,
+                            None => (value * 255.0) as u8,
+                        };
+                        [value; 3]
+                    })
+                    .collect();
+                mapped.set_alpha_image(Arc::new(RtwImage::from_rgb(
+                    rgb.image_width,
+                    rgb.image_height,
+                    data,
+                )));
+            }
+            _ => mapped.set_opacity(match cutoff {
+                Some(cutoff) if factor >= cutoff => 1.0,
+                Some(_) => 0.0,
+                None => factor as f64,
+            }),
+        }
+    }
+    Arc::new(mapped)
+}
+
+fn warn_uv_mismatch(material: &gltf::Material) {
+    let mapping = uv_mapping(material);
+    let pbr = material.pbr_metallic_roughness();
+    let mut mismatched: Vec<&str> = Vec::new();
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        if UvMapping::from_info(&info) != mapping {
+            mismatched.push("metallic-roughness");
+        }
+    }
+    if let Some(info) = material.emissive_texture() {
+        if UvMapping::from_info(&info) != mapping {
+            mismatched.push("emissive");
+        }
+    }
+    if let Some(normal) = material.normal_texture() {
+        if normal.tex_coord() != mapping.set || mapping != UvMapping::default() {
+            mismatched.push("normal");
+        }
+    }
+    if !mismatched.is_empty() {
+        eprintln!(
+            "Warning: glTF material {:?} maps its {} texture(s) with a different TEXCOORD set or transform than the base color; using the base color mapping.",
+            material.name().unwrap_or("<unnamed>"),
+            mismatched.join(", ")
+        );
+    }
+}
+
+fn warn_dropped_textures(material: &gltf::Material) {
+    if material.occlusion_texture().is_some() {
+        eprintln!(
+            "Warning: glTF material {:?} has an occlusion texture, which is ignored; occlusion comes from the traced geometry.",
+            material.name().unwrap_or("<unnamed>")
+        );
+    }
+}
+
+fn solid_value(value: f64) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new_color(&Color::new(value, value, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::interval::Interval;
+    use crate::material::ScatterRecord;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use crate::utility::INFINITY;
+
+    // 单位直角三角形 (缓冲区内嵌为 data URI), 节点放大 2 倍并平移到 (2, 0, -3);
+    // 纯金属镜面材质, 法线贴图是 1x1 的 (255, 128, 255); 另有一个点光源在 (0, 5, 0)
+    const EMBEDDED_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "point", "color": [1.0, 0.5, 0.25], "intensity": 683.0 }]
+            }
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 1] }],
+        "nodes": [
+            { "mesh": 0, "translation": [2.0, 0.0, -3.0], "scale": [2.0, 2.0, 2.0] },
+            { "translation": [0.0, 5.0, 0.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+        ],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+        "materials": [{
+            "name": "mirror",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [0.8, 0.4, 0.2, 1.0],
+                "metallicFactor": 1.0,
+                "roughnessFactor": 0.0
+            },
+            "normalTexture": { "index": 0, "scale": 0.5 },
+            "occlusionTexture": { "index": 0 }
+        }],
+        "textures": [{ "source": 0 }],
+        "images": [{ "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP43/AfAAUAAn8gmLxdAAAAAElFTkSuQmCC" }],
+        "buffers": [{
+            "byteLength": 44,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+        }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ]
+    }"#;
+
+    fn load_embedded() -> GltfScene {
+        let dir = std::env::temp_dir().join(format!("raytracer-{}-gltf", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("embedded.gltf"), EMBEDDED_GLTF).unwrap();
+        let mut search_paths = SearchPaths::new();
+        search_paths.add(&dir);
+        let scene = load_gltf("embedded.gltf", &search_paths);
+        std::fs::remove_dir_all(&dir).unwrap();
+        scene.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[test]
+    fn embedded_gltf_maps_transform_material_and_light() {
+        let scene = load_embedded();
+        let mut sampler = IndependentSampler::new(0);
+        let ray_t = Interval::new(0.001, INFINITY);
+
+        // 局部坐标 (0.6, 0.2) 只有经过放大后才落在三角形内
+        let r = Ray::new(Vec3::new(3.2, 0.4, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        assert!(scene.world.hit(&r, &ray_t, &mut rec, &mut sampler));
+        assert!((rec.t - 3.0).abs() < 1e-6);
+        assert!((rec.geometric_normal.z - 1.0).abs() < 1e-9);
+        // 法线贴图的 XY 按 0.5 缩放: 倾斜后 cos = 1 / sqrt(1 + 0.5²)
+        let cos = rec.normal.dot(&rec.geometric_normal);
+        assert!((cos - 1.0 / 1.25f64.sqrt()).abs() < 1e-3, "cos {}", cos);
+        // metallic = 1, roughness = 0: 总是镜面反射, 衰减等于基础色
+        let mut srec = ScatterRecord::default();
+        assert!(rec.mat.scatter(&r, &rec, &mut srec, &mut sampler));
+        assert!(srec.skip_pdf);
+        let expected = Color::new(0.8, 0.4, 0.2);
+        assert!((srec.attenuation - expected).length() < 1e-6);
+
+        let r = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut rec = HitRecord::default();
+        assert!(scene.lights.hit(&r, &ray_t, &mut rec, &mut sampler));
+        assert!((rec.p.y - (5.0 + PUNCTUAL_LIGHT_RADIUS)).abs() < 1e-9);
+        let emitted = rec.mat.emitted(&r, &rec, rec.u, rec.v, &rec.p);
+        let radiance = 1.0 / (PI * PUNCTUAL_LIGHT_RADIUS * PUNCTUAL_LIGHT_RADIUS);
+        assert!((emitted - Color::new(1.0, 0.5, 0.25) * radiance).length() < 1e-6);
+    }
+}
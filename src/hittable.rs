@@ -1,8 +1,11 @@
 use crate::aabb::Aabb;
 use crate::color::Color;
 use crate::interval::Interval;
+use crate::mat4::Mat4;
 use crate::material::{Lambertian, Material};
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::utility::{INFINITY, degrees_to_radians};
 use crate::vec3::Vec3;
 use std::sync::Arc;
@@ -11,11 +14,16 @@ use std::sync::Arc;
 pub struct HitRecord {
     pub p: Vec3,
     pub normal: Vec3,
+    pub geometric_normal: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub color: Color,
     pub t: f64,
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
     pub mat: Arc<dyn Material>,
+    pub material_override: Option<Arc<dyn Material>>,
 }
 
 impl HitRecord {
@@ -31,21 +39,67 @@ impl HitRecord {
         HitRecord {
             p,
             normal,
+            geometric_normal: normal,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            color: Color::new(1.0, 1.0, 1.0),
             t,
             u,
             v,
             front_face,
             mat: mat.clone(),
+            material_override: None,
+        }
+    }
+    // 实例可覆盖共享 BLAS 的材质, 图元须在 alpha 测试和法线扰动之前通过这里设置材质
+    pub fn set_material(&mut self, mat: &Arc<dyn Material>) {
+        self.mat = self
+            .material_override
+            .clone()
+            .unwrap_or_else(|| mat.clone());
+    }
+    pub fn scratch(&self) -> HitRecord {
+        HitRecord {
+            material_override: self.material_override.clone(),
+            ..HitRecord::default()
         }
     }
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3, u: f64, v: f64) {
-        let normal = outward_normal + self.mat.get_normal(u, v);
-        if Vec3::dot(&r.direction, &normal) < 0.0 {
-            self.front_face = true;
-            self.normal = normal.unit();
+        let uvw = Onb::new(&outward_normal);
+        self.u = u;
+        self.v = v;
+        self.set_shading_frame(r, outward_normal, outward_normal, uvw.u(), uvw.v());
+    }
+    pub fn set_shading_frame(
+        &mut self,
+        r: &Ray,
+        outward_normal: Vec3,
+        shading_normal: Vec3,
+        dpdu: Vec3,
+        dpdv: Vec3,
+    ) {
+        self.dpdu = dpdu;
+        self.dpdv = dpdv;
+        let normal = self
+            .mat
+            .perturb_normal(&shading_normal.unit(), &dpdu, &dpdv, self.u, self.v);
+        self.front_face = Vec3::dot(&r.direction, &outward_normal) < 0.0;
+        if self.front_face {
+            self.geometric_normal = outward_normal.unit();
+            self.normal = normal;
         } else {
-            self.front_face = false;
-            self.normal = -normal.unit();
+            self.geometric_normal = -outward_normal.unit();
+            self.normal = -normal;
+        }
+        // 插值或扰动后的着色法线可能背离几何法线或视线 (轮廓处, 强凹凸),
+        // 先翻到几何法线一侧, 再向视线方向弯折到地平线之上
+        if self.normal.dot(&self.geometric_normal) < 0.0 {
+            self.normal = -self.normal;
+        }
+        let wo = -r.direction.unit();
+        let cos_wo = self.normal.dot(&wo);
+        if cos_wo < SHADING_NORMAL_EPSILON {
+            self.normal = (self.normal + wo * (SHADING_NORMAL_EPSILON - cos_wo)).unit();
         }
     }
 }
@@ -54,25 +108,98 @@ impl Default for HitRecord {
         HitRecord {
             p: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 0.0, 0.0),
+            geometric_normal: Vec3::new(0.0, 0.0, 0.0),
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
+            color: Color::new(1.0, 1.0, 1.0),
             t: 0.0,
             u: 0.0,
             v: 0.0,
             front_face: false,
             mat: Arc::new(Lambertian::new(Color::default())),
+            material_override: None,
         }
     }
 }
 
+const MAX_HITS_ALONG_RAY: usize = 64;
+const SHADING_NORMAL_EPSILON: f64 = 1e-3;
+
 pub trait Hittable: Send + Sync {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool;
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool;
+    // 沿光线依次收集所有交点; 每个交点都从 outer 派生, 以继承外层实例的覆盖材质
+    fn hit_all(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        outer: &HitRecord,
+        hits: &mut Vec<HitRecord>,
+        sampler: &mut dyn Sampler,
+    ) {
+        let step = 0.0001 / r.direction.length();
+        let mut t_min = ray_t.min;
+        let mut rec = outer.scratch();
+        while hits.len() < MAX_HITS_ALONG_RAY
+            && self.hit(r, &Interval::new(t_min, ray_t.max), &mut rec, sampler)
+        {
+            t_min = rec.t + step;
+            hits.push(rec.clone());
+        }
+    }
+    // 任意命中查询 (阴影光线): 只判断区间内是否有遮挡, 命中即可提前返回, 同样遵守透明度测试.
+    // 目前 ray_color 经 MixturePDF 采样光源方向后仍需完整求交以取得发光值, 尚无调用者,
+    // 留给显式光源采样 (NEE) 使用
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        let mut rec = HitRecord::default();
+        self.hit(r, ray_t, &mut rec, sampler)
+    }
     fn bounding_box(&self) -> Aabb;
-    fn pdf_value(&self, _origin: &Vec3, _direction: &Vec3) -> f64 {
+    fn pdf_value(&self, _origin: &Vec3, _direction: &Vec3, _sampler: &mut dyn Sampler) -> f64 {
         0.0
     }
-    fn random(&self, _origin: &Vec3) -> Vec3 {
+    fn random(&self, _origin: &Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
 }
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        (**self).hit(r, ray_t, rec, sampler)
+    }
+    fn hit_all(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        outer: &HitRecord,
+        hits: &mut Vec<HitRecord>,
+        sampler: &mut dyn Sampler,
+    ) {
+        (**self).hit_all(r, ray_t, outer, hits, sampler)
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        (**self).occluded(r, ray_t, sampler)
+    }
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        (**self).pdf_value(origin, direction, sampler)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).random(origin, sampler)
+    }
+}
 
 pub struct Translate {
     object: Arc<dyn Hittable>,
@@ -89,14 +216,24 @@ impl Translate {
     }
 }
 impl Hittable for Translate {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
         let offset_r = Ray::new_time(r.origin - self.offset, r.direction, r.tm);
-        if !self.object.hit(&offset_r, ray_t, rec) {
+        if !self.object.hit(&offset_r, ray_t, rec, sampler) {
             return false;
         }
         rec.p = rec.p + self.offset;
         true
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        let offset_r = Ray::new_time(r.origin - self.offset, r.direction, r.tm);
+        self.object.occluded(&offset_r, ray_t, sampler)
+    }
     fn bounding_box(&self) -> Aabb {
         self.bbox
     }
@@ -141,9 +278,7 @@ impl RotateY {
             bbox,
         }
     }
-}
-impl Hittable for RotateY {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn rotate_ray(&self, r: &Ray) -> Ray {
         let origin = Vec3::new(
             self.cos_theta * r.origin.x - self.sin_theta * r.origin.z,
             r.origin.y,
@@ -154,8 +289,19 @@ impl Hittable for RotateY {
             r.direction.y,
             self.sin_theta * r.direction.x + self.cos_theta * r.direction.z,
         );
-        let rotated_r = Ray::new_time(origin, direction, r.tm);
-        if !self.object.hit(&rotated_r, ray_t, rec) {
+        Ray::new_time(origin, direction, r.tm)
+    }
+}
+impl Hittable for RotateY {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let rotated_r = self.rotate_ray(r);
+        if !self.object.hit(&rotated_r, ray_t, rec, sampler) {
             return false;
         }
         rec.p = Vec3::new(
@@ -168,9 +314,249 @@ impl Hittable for RotateY {
             rec.normal.y,
             -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z,
         );
+        rec.geometric_normal = Vec3::new(
+            self.cos_theta * rec.geometric_normal.x + self.sin_theta * rec.geometric_normal.z,
+            rec.geometric_normal.y,
+            -self.sin_theta * rec.geometric_normal.x + self.cos_theta * rec.geometric_normal.z,
+        );
+        rec.dpdu = Vec3::new(
+            self.cos_theta * rec.dpdu.x + self.sin_theta * rec.dpdu.z,
+            rec.dpdu.y,
+            -self.sin_theta * rec.dpdu.x + self.cos_theta * rec.dpdu.z,
+        );
+        rec.dpdv = Vec3::new(
+            self.cos_theta * rec.dpdv.x + self.sin_theta * rec.dpdv.z,
+            rec.dpdv.y,
+            -self.sin_theta * rec.dpdv.x + self.cos_theta * rec.dpdv.z,
+        );
         true
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.object.occluded(&self.rotate_ray(r), ray_t, sampler)
+    }
     fn bounding_box(&self) -> Aabb {
         self.bbox
     }
 }
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+    bbox: Aabb,
+}
+impl Transform {
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        let inverse = matrix.inverse();
+        let bbox = object.bounding_box();
+        let mut min = Vec3::new(INFINITY, INFINITY, INFINITY);
+        let mut max = Vec3::new(-INFINITY, -INFINITY, -INFINITY);
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let x = i as f64 * bbox.x.max + (1.0 - i as f64) * bbox.x.min;
+                    let y = j as f64 * bbox.y.max + (1.0 - j as f64) * bbox.y.min;
+                    let z = k as f64 * bbox.z.max + (1.0 - k as f64) * bbox.z.min;
+                    let tester = matrix.transform_point(&Vec3::new(x, y, z));
+                    min.x = min.x.min(tester.x);
+                    min.y = min.y.min(tester.y);
+                    min.z = min.z.min(tester.z);
+                    max.x = max.x.max(tester.x);
+                    max.y = max.y.max(tester.y);
+                    max.z = max.z.max(tester.z);
+                }
+            }
+        }
+        Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox: Aabb::new_points(&min, &max),
+        }
+    }
+    pub fn then(self, matrix: Mat4) -> Self {
+        Self::new(self.object, matrix * self.matrix)
+    }
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::new_time(
+            self.inverse.transform_point(&r.origin),
+            self.inverse.transform_vector(&r.direction),
+            r.tm,
+        )
+    }
+}
+impl Hittable for Transform {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        if !self.object.hit(&self.object_ray(r), ray_t, rec, sampler) {
+            return false;
+        }
+        rec.p = self.matrix.transform_point(&rec.p);
+        rec.normal = self.normal_matrix.transform_vector(&rec.normal).unit();
+        rec.geometric_normal = self
+            .normal_matrix
+            .transform_vector(&rec.geometric_normal)
+            .unit();
+        rec.dpdu = self.matrix.transform_vector(&rec.dpdu);
+        rec.dpdv = self.matrix.transform_vector(&rec.dpdv);
+        true
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.object.occluded(&self.object_ray(r), ray_t, sampler)
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        let object_direction = self.inverse.transform_vector(direction).unit();
+        let pdf = self.object.pdf_value(
+            &self.inverse.transform_point(origin),
+            &object_direction,
+            sampler,
+        );
+        let stretch = self.matrix.transform_vector(&object_direction).length();
+        pdf * stretch * stretch * stretch / self.matrix.determinant3().abs()
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let object_origin = self.inverse.transform_point(origin);
+        self.matrix
+            .transform_vector(&self.object.random(&object_origin, sampler))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cone::Cone;
+    use crate::cylinder::Cylinder;
+    use crate::disk::Disk;
+    use crate::material::MappedMaterial;
+    use crate::paraboloid::Paraboloid;
+    use crate::quad::Quad;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::torus::Torus;
+
+    fn primitives(mat: Arc<dyn Material>) -> Vec<Arc<dyn Hittable>> {
+        let sphere: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, mat.clone()));
+        vec![
+            sphere.clone(),
+            Arc::new(Quad::new(
+                &Vec3::new(-1.0, -1.0, 0.0),
+                &Vec3::new(2.0, 0.0, 0.0),
+                &Vec3::new(0.0, 2.0, 0.0),
+                mat.clone(),
+            )),
+            Arc::new(Disk::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                1.0,
+                mat.clone(),
+            )),
+            Arc::new(Cylinder::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                1.0,
+                2.0,
+                true,
+                mat.clone(),
+            )),
+            Arc::new(Cone::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                1.0,
+                2.0,
+                true,
+                mat.clone(),
+            )),
+            Arc::new(Torus::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 0.3, mat.clone())),
+            Arc::new(Paraboloid::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                1.0,
+                2.0,
+                mat.clone(),
+            )),
+            Arc::new(Translate::new(sphere.clone(), Vec3::new(0.5, 0.0, 0.0))),
+            Arc::new(RotateY::new(sphere.clone(), 30.0)),
+            Arc::new(Transform::new(
+                sphere,
+                Mat4::rotate_x(20.0) * Mat4::scale(&Vec3::new(1.0, 0.5, 2.0)),
+            )),
+        ]
+    }
+
+    #[test]
+    fn occluded_agrees_with_hit() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut sampler = IndependentSampler::new(3);
+        for object in primitives(mat) {
+            for _ in 0..500 {
+                let origin = Vec3::random_range(-3.0, 3.0, &mut sampler);
+                let r = Ray::new(origin, Vec3::random_unit_vector(&mut sampler));
+                let ray_t = Interval::new(0.001, sampler.get_1d() * 4.0);
+                let mut rec = HitRecord::default();
+                let hit = object.hit(&r, &ray_t, &mut rec, &mut sampler);
+                assert_eq!(object.occluded(&r, &ray_t, &mut sampler), hit);
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_surfaces_do_not_occlude() {
+        let mut transparent =
+            MappedMaterial::new(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))));
+        transparent.set_opacity(0.0);
+        let mut sampler = IndependentSampler::new(5);
+        for object in primitives(Arc::new(transparent)) {
+            for _ in 0..100 {
+                let r = Ray::new(
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Vec3::random_unit_vector(&mut sampler),
+                );
+                assert!(!object.occluded(&r, &Interval::new(0.001, INFINITY), &mut sampler));
+            }
+        }
+    }
+
+    #[test]
+    fn transformed_sphere_has_ellipsoid_normals() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, mat));
+        let axes = Vec3::new(2.0, 1.0, 0.5);
+        let ellipsoid = Transform::new(
+            sphere,
+            Mat4::translate(&Vec3::new(0.0, 1.0, 0.0)) * Mat4::scale(&axes),
+        );
+        let mut sampler = IndependentSampler::new(8);
+        for _ in 0..200 {
+            let target = Vec3::random_range(-0.3, 0.3, &mut sampler) + Vec3::new(0.0, 1.0, 0.0);
+            let origin = Vec3::random_unit_vector(&mut sampler) * 10.0;
+            let r = Ray::new(origin, target - origin);
+            let mut rec = HitRecord::default();
+            assert!(ellipsoid.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+            // 椭球面 (x/a)^2 + ((y-1)/b)^2 + (z/c)^2 = 1 的梯度方向
+            let local = rec.p - Vec3::new(0.0, 1.0, 0.0);
+            let implicit = (local.x / axes.x).powi(2)
+                + (local.y / axes.y).powi(2)
+                + (local.z / axes.z).powi(2);
+            assert!((implicit - 1.0).abs() < 1e-9);
+            let gradient = Vec3::new(
+                local.x / (axes.x * axes.x),
+                local.y / (axes.y * axes.y),
+                local.z / (axes.z * axes.z),
+            )
+            .unit();
+            assert!(
+                (rec.normal - gradient).length() < 1e-9,
+                "{:?} vs {:?}",
+                rec.normal,
+                gradient
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::utility::random_int_range;
 use crate::vec3::Vec3;
 use std::sync::Arc;
@@ -28,12 +29,23 @@ impl HittableList {
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        let mut temp_rec = HitRecord::default();
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let mut temp_rec = rec.scratch();
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
         for object in &self.objects {
-            if object.hit(r, &Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
+            if object.hit(
+                r,
+                &Interval::new(ray_t.min, closest_so_far),
+                &mut temp_rec,
+                sampler,
+            ) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 *rec = temp_rec.clone();
@@ -41,18 +53,24 @@ impl Hittable for HittableList {
         }
         hit_anything
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.objects
+            .iter()
+            .any(|object| object.occluded(r, ray_t, sampler))
+    }
     fn bounding_box(&self) -> Aabb {
         self.bbox
     }
-    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
         let weight = 1.0 / self.objects.len() as f64;
         let mut sum = 0.0;
         for object in self.objects.iter() {
-            sum += weight * object.pdf_value(origin, direction);
+            sum += weight * object.pdf_value(origin, direction, sampler);
         }
         sum
     }
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        self.objects[random_int_range(0, self.objects.len() as i32 - 1) as usize].random(origin)
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.objects[random_int_range(0, self.objects.len() as i32 - 1, sampler) as usize]
+            .random(origin, sampler)
     }
 }
@@ -0,0 +1,126 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::hittable::{HitRecord, Hittable, Transform};
+use crate::interval::Interval;
+use crate::mat4::Mat4;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Instance {
+    object: Transform,
+    material: Option<Arc<dyn Material>>,
+}
+impl Instance {
+    pub fn new(blas: &Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        Self {
+            object: Transform::new(blas.clone(), matrix),
+            material: None,
+        }
+    }
+    pub fn with_material(mut self, material: Arc<dyn Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+impl Hittable for Instance {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some(material) = &self.material else {
+            return self.object.hit(r, ray_t, rec, sampler);
+        };
+        let outer = rec.material_override.replace(material.clone());
+        let hit = self.object.hit(r, ray_t, rec, sampler);
+        rec.material_override = outer;
+        hit
+    }
+    // 覆盖材质的透明度只能在完整命中时检查
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        match &self.material {
+            Some(material) => {
+                let mut rec = HitRecord {
+                    material_override: Some(material.clone()),
+                    ..HitRecord::default()
+                };
+                self.object.hit(r, ray_t, &mut rec, sampler)
+            }
+            None => self.object.occluded(r, ray_t, sampler),
+        }
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        self.object.pdf_value(origin, direction, sampler)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin, sampler)
+    }
+}
+
+pub fn build_tlas(instances: Vec<Instance>) -> Bvh<Instance> {
+    Bvh::new(instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::csg::{Csg, CsgOp};
+    use crate::material::{DiffuseLight, Lambertian, MappedMaterial};
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::utility::INFINITY;
+
+    #[test]
+    fn occluded_uses_the_override_material() {
+        let opaque: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut transparent = MappedMaterial::new(opaque.clone());
+        transparent.set_opacity(0.0);
+        let transparent: Arc<dyn Material> = Arc::new(transparent);
+        let blas = |mat: &Arc<dyn Material>| -> Arc<dyn Hittable> {
+            Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, mat.clone()))
+        };
+        let r = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let ray_t = Interval::new(0.001, INFINITY);
+        let mut sampler = IndependentSampler::new(0);
+        let hidden =
+            Instance::new(&blas(&opaque), Mat4::identity()).with_material(transparent.clone());
+        assert!(!hidden.occluded(&r, &ray_t, &mut sampler));
+        let mut rec = HitRecord::default();
+        assert!(!hidden.hit(&r, &ray_t, &mut rec, &mut sampler));
+        let shown = Instance::new(&blas(&transparent), Mat4::identity()).with_material(opaque);
+        assert!(shown.occluded(&r, &ray_t, &mut sampler));
+        assert!(shown.hit(&r, &ray_t, &mut rec, &mut sampler));
+    }
+
+    #[test]
+    fn override_reaches_csg_children() {
+        let own: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let glow = Color::new(2.0, 3.0, 4.0);
+        let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(&glow));
+        let sphere = |x: f64| -> Arc<dyn Hittable> {
+            Arc::new(Sphere::new(Vec3::new(x, 0.0, 0.0), 1.0, own.clone()))
+        };
+        let csg: Arc<dyn Hittable> =
+            Arc::new(Csg::new(sphere(-0.5), sphere(0.5), CsgOp::Difference));
+        let instance =
+            Instance::new(&csg, Mat4::translate(&Vec3::new(0.0, 0.0, -2.0))).with_material(light);
+        let r = Ray::new(Vec3::new(-5.0, 0.0, -2.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(instance.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+        assert!((rec.t - 3.5).abs() < 1e-9);
+        let emitted = rec.mat.emitted(&r, &rec, rec.u, rec.v, &rec.p);
+        assert!((emitted - glow).length() < 1e-12);
+        // 覆盖只作用于实例内部, 外层记录不受影响
+        assert!(rec.material_override.is_none());
+    }
+}
@@ -2,45 +2,83 @@
 extern crate lazy_static;
 
 mod aabb;
+mod asset;
 mod bvh;
 mod camera;
 mod color;
+mod cone;
 mod constant_medium;
+mod csg;
+mod cylinder;
+mod disk;
+mod film;
+mod filter;
+mod gltf_import;
 mod hittable;
 mod hittable_list;
+mod instance;
 mod interval;
+mod mat4;
 mod material;
+mod medium;
 mod obj;
 mod onb;
+mod paraboloid;
 mod pdf;
 mod perlin;
+mod ply;
 mod quad;
 mod ray;
 mod rtw_stb_image;
+mod sampler;
+mod sdf;
 mod sphere;
+mod stl;
 mod texture;
+mod torus;
 mod triangle;
 mod utility;
 mod vec3;
 
-use crate::bvh::BvhNode;
+use crate::aabb::Aabb;
+use crate::asset::SearchPaths;
+use crate::bvh::Bvh;
 use crate::camera::Camera;
 use crate::color::Color;
+use crate::cone::Cone;
 use crate::constant_medium::ConstantMedium;
-use crate::hittable::{RotateY, Translate};
-use crate::material::{Dielectric, DiffuseLight, Lambertian, MappedMaterial, Metal};
-use crate::obj::load_model;
+use crate::csg::{Csg, CsgOp};
+use crate::cylinder::Cylinder;
+use crate::disk::Disk;
+use crate::filter::FilterType;
+use crate::gltf_import::load_gltf;
+use crate::hittable::{RotateY, Transform, Translate};
+use crate::instance::{Instance, build_tlas};
+use crate::mat4::Mat4;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, MappedMaterial, Metal, Subsurface};
+use crate::obj::{load_blas_from, load_model};
+use crate::paraboloid::Paraboloid;
+use crate::ply::load_ply;
 use crate::quad::{Quad, make_box};
-use crate::texture::{ImageTexture, NoiseTexture};
+use crate::sampler::IndependentSampler;
+use crate::sdf::{
+    SdfBox, SdfCapsule, SdfEllipsoid, SdfObject, SdfSphere, SdfTorus, SmoothIntersection,
+    SmoothSubtraction, SmoothUnion,
+};
+use crate::stl::load_stl;
+use crate::texture::{ImageTexture, NoiseTexture, VertexColor};
+use crate::torus::Torus;
 use crate::utility::{degrees_to_radians, random_double_range};
 use hittable::Hittable;
 use hittable_list::HittableList;
+use rayon::prelude::*;
 use sphere::Sphere;
 use std::sync::Arc;
 use std::time::Instant;
 use vec3::Vec3;
 
 fn book2_final_scene(image_width: u32, samples_per_pixel: u32, max_depth: u32) {
+    let mut sampler = IndependentSampler::new(0);
     let mut boxes1 = HittableList::new();
     let ground = Arc::new(Lambertian::new(Color::new(0.48, 0.83, 0.53)));
     let boxes_per_size = 20;
@@ -51,7 +89,7 @@ fn book2_final_scene(image_width: u32, samples_per_pixel: u32, max_depth: u32) {
             let z0 = -1000.0 + j as f64 * w;
             let y0 = 0.0;
             let x1 = x0 + w;
-            let y1 = random_double_range(1.0, 101.0);
+            let y1 = random_double_range(1.0, 101.0, &mut sampler);
             let z1 = z0 + w;
             boxes1.add(make_box(
                 &Vec3::new(x0, y0, z0),
@@ -61,7 +99,7 @@ fn book2_final_scene(image_width: u32, samples_per_pixel: u32, max_depth: u32) {
         }
     }
     let mut world = HittableList::new();
-    world.add(Arc::new(BvhNode::new_list(&mut boxes1)));
+    world.add(Arc::new(Bvh::new_list(&boxes1)));
     let light = Arc::new(DiffuseLight::new(&Color::new(7.0, 7.0, 7.0)));
     world.add(Arc::new(Quad::new(
         &Vec3::new(123.0, 554.0, 147.0),
@@ -125,7 +163,7 @@ fn book2_final_scene(image_width: u32, samples_per_pixel: u32, max_depth: u32) {
         100.0,
         emat,
     )));
-    let pertext = Arc::new(NoiseTexture::new(0.2));
+    let pertext = Arc::new(NoiseTexture::new(0.2, &mut sampler));
     world.add(Arc::new(Sphere::new(
         Vec3::new(220.0, 280.0, 300.0),
         80.0,
@@ -136,13 +174,13 @@ fn book2_final_scene(image_width: u32, samples_per_pixel: u32, max_depth: u32) {
     let ns = 1000;
     for _j in 0..ns {
         boxes2.add(Arc::new(Sphere::new(
-            Vec3::random_range(0.0, 165.0),
+            Vec3::random_range(0.0, 165.0, &mut sampler),
             10.0,
             white.clone(),
         )));
     }
     world.add(Arc::new(Translate::new(
-        Arc::new(RotateY::new(Arc::new(BvhNode::new_list(&mut boxes2)), 15.0)),
+        Arc::new(RotateY::new(Arc::new(Bvh::new_list(&boxes2)), 15.0)),
         Vec3::new(-100.0, 270.0, 395.0),
     )));
     let mut cam = Camera::default();
@@ -264,7 +302,7 @@ fn book3_cornell_box() {
     cam.render(&world_arc, &lights_arc, path);
 }
 fn obj_test() {
-    let mut world = load_model("cornell_box.obj", 1.0);
+    let mut world = load_model("cornell_box.obj", 1.0).unwrap_or_else(|err| panic!("{}", err));
     let light = DiffuseLight::new(&Color::new(15.0, 15.0, 15.0));
     world.add(Arc::new(Quad::new(
         &Vec3::new(213.0, 548.0, 227.0),
@@ -372,6 +410,14 @@ fn normal_mapping_test() {
     let box2 = Arc::new(RotateY::new(box2, -18.0));
     let box2 = Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0)));
     world.add(box2);
+    // 凹凸贴图球
+    let mut bumpy = MappedMaterial::new(Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73))));
+    bumpy.set_bump("moon.png", 8.0);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(212.0, 235.0, 147.0),
+        70.0,
+        Arc::new(bumpy),
+    )));
     let mut cam = Camera::default();
     cam.aspect_ratio = 1.0;
     cam.image_width = 600;
@@ -461,7 +507,7 @@ fn all_mapping_test() {
         90.0,
         Arc::new(light2),
     )));
-    // let mut world = bvh::BvhNode::new_list(&mut world);
+    // let mut world = bvh::Bvh::new_list(&world);
     let mut cam = Camera::default();
     cam.aspect_ratio = 1.0;
     cam.image_width = 600;
@@ -480,7 +526,408 @@ fn all_mapping_test() {
     let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
     cam.render(&world_arc, &lights_arc, path);
 }
+fn cornell_box_room(world: &mut HittableList, lights: &mut HittableList) {
+    let red = Lambertian::new(Color::new(0.65, 0.05, 0.05));
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Lambertian::new(Color::new(0.12, 0.45, 0.15));
+    let light = DiffuseLight::new(&Color::new(15.0, 15.0, 15.0));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 555.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        Arc::new(green),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(0.0, 0.0, 555.0),
+        &Vec3::new(0.0, 0.0, -555.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        Arc::new(red),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(213.0, 554.0, 227.0),
+        &Vec3::new(130.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 105.0),
+        Arc::new(light),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(0.0, 555.0, 0.0),
+        &Vec3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(0.0, 0.0, 555.0),
+        &Vec3::new(555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(555.0, 0.0, 555.0),
+        &Vec3::new(-555.0, 0.0, 0.0),
+        &Vec3::new(0.0, 555.0, 0.0),
+        white,
+    )));
+    let empty_material = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+    lights.add(Arc::new(Quad::new(
+        &Vec3::new(343.0, 554.0, 332.0),
+        &Vec3::new(-130.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -105.0),
+        empty_material,
+    )));
+}
+fn cornell_box_camera(samples_per_pixel: u32) -> Camera {
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = samples_per_pixel;
+    cam.max_depth = 50;
+    cam.vfov = 40.0;
+    cam.lookfrom = Vec3::new(278.0, 278.0, -800.0);
+    cam.lookat = Vec3::new(278.0, 278.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.focus_dist = 10.0;
+    cam.background = Color::new(0.0, 0.0, 0.0);
+    cam
+}
+fn absorbing_medium_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    // 有色玻璃砖
+    let tinted_glass = Dielectric::new_absorbing(1.5, Color::new(0.012, 0.002, 0.008), 0);
+    let glass_box = make_box(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 330.0, 165.0),
+        Arc::new(tinted_glass),
+    );
+    let glass_box = Arc::new(RotateY::new(glass_box, 15.0));
+    let glass_box = Arc::new(Translate::new(glass_box, Vec3::new(265.0, 0.0, 295.0)));
+    world.add(glass_box);
+    // 玻璃杯中的水: 杯壁优先级高于水, 水与杯壁重叠的部分按杯壁处理
+    let glass = Dielectric::new_absorbing(1.5, Color::new(0.0, 0.0, 0.0), 2);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(190.0, 90.0, 190.0),
+        90.0,
+        Arc::new(glass),
+    )));
+    let water = Dielectric::new_absorbing(1.33, Color::new(0.02, 0.006, 0.002), 1);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(190.0, 85.0, 190.0),
+        80.0,
+        Arc::new(water),
+    )));
+    let mut cam = cornell_box_camera(100);
+    let path = std::path::Path::new("output/test_absorbing_medium.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn subsurface_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    // 玉石
+    let jade = Subsurface::new(
+        Color::new(0.8, 0.99, 0.85),
+        Color::new(12.0, 30.0, 16.0),
+        1.5,
+    );
+    let jade_box = make_box(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 330.0, 165.0),
+        Arc::new(jade),
+    );
+    let jade_box = Arc::new(RotateY::new(jade_box, 15.0));
+    let jade_box = Arc::new(Translate::new(jade_box, Vec3::new(265.0, 0.0, 295.0)));
+    world.add(jade_box);
+    // 皮肤
+    let skin = Subsurface::new(Color::new(0.99, 0.9, 0.8), Color::new(20.0, 8.0, 4.0), 1.4);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(190.0, 90.0, 190.0),
+        90.0,
+        Arc::new(skin),
+    )));
+    let mut cam = cornell_box_camera(100);
+    cam.max_depth = 128;
+    let path = std::path::Path::new("output/test_subsurface.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn sdf_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    // 圆角方块与圆环平滑并集
+    let rounded_box = Arc::new(SdfBox::new_rounded(
+        Vec3::new(400.0, 60.0, 380.0),
+        Vec3::new(80.0, 60.0, 80.0),
+        15.0,
+    ));
+    let torus = Arc::new(SdfTorus::new(Vec3::new(400.0, 140.0, 380.0), 70.0, 18.0));
+    let blob = SmoothUnion::new(rounded_box, torus, 20.0);
+    world.add(Arc::new(SdfObject::new(
+        Arc::new(blob),
+        Arc::new(Lambertian::new(Color::new(0.8, 0.6, 0.2))),
+        Aabb::new_points(
+            &Vec3::new(300.0, 0.0, 280.0),
+            &Vec3::new(500.0, 180.0, 480.0),
+        ),
+    )));
+    // 方块减去球
+    let cube = Arc::new(SdfBox::new(
+        Vec3::new(150.0, 70.0, 200.0),
+        Vec3::new(70.0, 70.0, 70.0),
+    ));
+    let hole = Arc::new(SdfSphere::new(Vec3::new(150.0, 140.0, 130.0), 80.0));
+    world.add(Arc::new(SdfObject::new(
+        Arc::new(SmoothSubtraction::new(cube, hole, 10.0)),
+        Arc::new(Metal::new(Color::new(0.8, 0.85, 0.88), 0.1)),
+        Aabb::new_points(
+            &Vec3::new(80.0, 0.0, 130.0),
+            &Vec3::new(220.0, 140.0, 270.0),
+        ),
+    )));
+    // 胶囊与椭球交集
+    let capsule = Arc::new(SdfCapsule::new(
+        Vec3::new(280.0, 250.0, 300.0),
+        Vec3::new(380.0, 350.0, 300.0),
+        50.0,
+    ));
+    let ellipsoid = Arc::new(SdfEllipsoid::new(
+        Vec3::new(330.0, 300.0, 300.0),
+        Vec3::new(100.0, 45.0, 60.0),
+    ));
+    world.add(Arc::new(SdfObject::new(
+        Arc::new(SmoothIntersection::new(capsule, ellipsoid, 5.0)),
+        Arc::new(Dielectric::new(1.5)),
+        Aabb::new_points(
+            &Vec3::new(230.0, 200.0, 250.0),
+            &Vec3::new(430.0, 400.0, 350.0),
+        ),
+    )));
+    let mut cam = cornell_box_camera(100);
+    let path = std::path::Path::new("output/test_sdf.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn primitives_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    let empty_material = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+    // 圆盘灯与灯管
+    let disk_light = Arc::new(DiffuseLight::new(&Color::new(6.0, 5.0, 4.0)));
+    world.add(Arc::new(Disk::new(
+        Vec3::new(450.0, 554.0, 450.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        60.0,
+        disk_light,
+    )));
+    lights.add(Arc::new(Disk::new(
+        Vec3::new(450.0, 554.0, 450.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        60.0,
+        empty_material.clone(),
+    )));
+    let tube_light = Arc::new(DiffuseLight::new(&Color::new(2.0, 4.0, 8.0)));
+    world.add(Arc::new(Cylinder::new(
+        Vec3::new(80.0, 0.0, 450.0),
+        10.0,
+        300.0,
+        true,
+        tube_light,
+    )));
+    lights.add(Arc::new(Cylinder::new(
+        Vec3::new(80.0, 0.0, 450.0),
+        10.0,
+        300.0,
+        true,
+        empty_material,
+    )));
+    // 各类解析几何体
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    world.add(Arc::new(Cylinder::new(
+        Vec3::new(400.0, 0.0, 350.0),
+        70.0,
+        160.0,
+        false,
+        Arc::new(Metal::new(Color::new(0.8, 0.6, 0.3), 0.2)),
+    )));
+    world.add(Arc::new(Cone::new(
+        Vec3::new(180.0, 0.0, 320.0),
+        80.0,
+        200.0,
+        true,
+        white.clone(),
+    )));
+    world.add(Arc::new(Torus::new(
+        Vec3::new(300.0, 300.0, 300.0),
+        90.0,
+        25.0,
+        Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    )));
+    world.add(Arc::new(Paraboloid::new(
+        Vec3::new(300.0, 0.0, 150.0),
+        60.0,
+        90.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    let mut cam = cornell_box_camera(100);
+    let path = std::path::Path::new("output/test_primitives.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn csg_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    // 方块与球的交集, 再挖去圆柱
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    let cube = make_box(
+        &Vec3::new(300.0, 0.0, 250.0),
+        &Vec3::new(460.0, 160.0, 410.0),
+        red.clone(),
+    );
+    let ball = Arc::new(Sphere::new(Vec3::new(380.0, 80.0, 330.0), 105.0, red));
+    let rounded = Arc::new(Csg::new(cube, ball, CsgOp::Intersection));
+    let drill = Arc::new(Cylinder::new(
+        Vec3::new(380.0, -10.0, 330.0),
+        45.0,
+        180.0,
+        true,
+        Arc::new(Lambertian::new(Color::new(0.9, 0.8, 0.2))),
+    ));
+    world.add(Arc::new(Csg::new(rounded, drill, CsgOp::Difference)));
+    // 两个玻璃球的并集, 内部不再有界面
+    let glass = Arc::new(Dielectric::new(1.5));
+    let left = Arc::new(Sphere::new(
+        Vec3::new(150.0, 90.0, 200.0),
+        80.0,
+        glass.clone(),
+    ));
+    let right = Arc::new(Sphere::new(Vec3::new(210.0, 140.0, 240.0), 70.0, glass));
+    world.add(Arc::new(Csg::new(left, right, CsgOp::Union)));
+    let mut cam = cornell_box_camera(100);
+    let path = std::path::Path::new("output/test_csg.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn transform_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    cornell_box_room(&mut world, &mut lights);
+    // 绕 x, z 轴旋转并非均匀缩放的方块
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let cube = make_box(
+        &Vec3::new(-50.0, -50.0, -50.0),
+        &Vec3::new(50.0, 50.0, 50.0),
+        white,
+    );
+    let cube = Transform::new(cube, Mat4::scale(&Vec3::new(1.0, 2.0, 1.0)))
+        .then(Mat4::rotate_x(30.0))
+        .then(Mat4::rotate_z(-20.0))
+        .then(Mat4::translate(&Vec3::new(380.0, 160.0, 350.0)));
+    world.add(Arc::new(cube));
+    // 椭球灯
+    let ellipsoid = Mat4::translate(&Vec3::new(170.0, 120.0, 250.0))
+        * Mat4::rotate_y(45.0)
+        * Mat4::scale(&Vec3::new(2.0, 0.6, 1.0));
+    let glow = Arc::new(DiffuseLight::new(&Color::new(4.0, 2.0, 1.0)));
+    world.add(Arc::new(Transform::new(
+        Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 40.0, glow)),
+        ellipsoid,
+    )));
+    let empty_material = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+    lights.add(Arc::new(Transform::new(
+        Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 40.0, empty_material)),
+        ellipsoid,
+    )));
+    let mut cam = cornell_box_camera(100);
+    let path = std::path::Path::new("output/test_transform.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn instancing_test() {
+    let mut sampler = IndependentSampler::new(0);
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    let ground = Arc::new(Lambertian::new(Color::new(0.48, 0.83, 0.53)));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(-2000.0, 0.0, -2000.0),
+        &Vec3::new(4000.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 4000.0),
+        ground,
+    )));
+    let light = Arc::new(DiffuseLight::new(&Color::new(10.0, 10.0, 10.0)));
+    world.add(Arc::new(Quad::new(
+        &Vec3::new(-300.0, 1500.0, -300.0),
+        &Vec3::new(600.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 600.0),
+        light,
+    )));
+    let empty_material = Arc::new(Lambertian::new(Color::default()));
+    lights.add(Arc::new(Quad::new(
+        &Vec3::new(-300.0, 1500.0, -300.0),
+        &Vec3::new(600.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 600.0),
+        empty_material,
+    )));
+    // 50 架无人机共享同一份 BVH
+    let mut search_paths = SearchPaths::new();
+    search_paths.add("assets");
+    let drone =
+        load_blas_from("drone.obj", 30.0, &search_paths).unwrap_or_else(|err| panic!("{}", err));
+    let gold = Arc::new(Metal::new(Color::new(0.9, 0.75, 0.3), 0.2));
+    let mut instances = Vec::new();
+    for i in 0..10 {
+        for j in 0..5 {
+            let matrix = Mat4::translate(&Vec3::new(
+                -900.0 + i as f64 * 200.0,
+                random_double_range(80.0, 400.0, &mut sampler),
+                j as f64 * 250.0,
+            )) * Mat4::rotate_y(random_double_range(0.0, 360.0, &mut sampler))
+                * Mat4::rotate_x(random_double_range(-15.0, 15.0, &mut sampler));
+            let instance = Instance::new(&drone, matrix);
+            if (i + j) % 4 == 0 {
+                instances.push(instance.with_material(gold.clone()));
+            } else {
+                instances.push(instance);
+            }
+        }
+    }
+    world.add(Arc::new(build_tlas(instances)));
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.vfov = 50.0;
+    cam.lookfrom = Vec3::new(0.0, 600.0, -1400.0);
+    cam.lookat = Vec3::new(0.0, 150.0, 500.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.focus_dist = 10.0;
+    cam.background = Color::new(0.5, 0.6, 0.8);
+    let path = std::path::Path::new("output/test_instancing.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
 fn final_scene() {
+    let mut sampler = IndependentSampler::new(0);
     let mut world = HittableList::new();
     let mut lights = HittableList::new();
 
@@ -622,29 +1069,33 @@ fn final_scene() {
     );
     world.add(Arc::new(dengpai));
 
-    // 阿米娅
-    let amiya = load_model("amiya.obj", 2.8);
-    let amiya = RotateY::new(Arc::new(amiya), 210.0);
-    let amiya = Translate::new(Arc::new(amiya), Vec3::new(475.0, 0.0, 580.0));
-    world.add(Arc::new(amiya));
-
-    // 夕泡泡
-    let longpao = load_model("arknights_dusk_plush_doll.obj", 170.0);
-    let longpao = RotateY::new(Arc::new(longpao), 135.0);
-    let longpao = Translate::new(Arc::new(longpao), Vec3::new(100.0, 165.0, 545.0));
-    world.add(Arc::new(longpao));
-
-    // 斯卡蒂
-    let sakaban = load_model("skadi.obj", 2.0);
-    let sakaban = RotateY::new(Arc::new(sakaban), 240.0);
-    let sakaban = Translate::new(Arc::new(sakaban), Vec3::new(750.0, 50.0, 420.0));
-    world.add(Arc::new(sakaban));
-
-    // 无人机
-    let drone = load_model("drone.obj", 30.0);
-    let drone = RotateY::new(Arc::new(drone), 70.0);
-    let drone = Translate::new(Arc::new(drone), Vec3::new(780.0, 300.0, 550.0));
-    world.add(Arc::new(drone));
+    // 阿米娅、夕泡泡、斯卡蒂、无人机, 缺失的模型跳过
+    let models = [
+        ("amiya.obj", 2.8, 210.0, Vec3::new(475.0, 0.0, 580.0)),
+        (
+            "arknights_dusk_plush_doll.obj",
+            170.0,
+            135.0,
+            Vec3::new(100.0, 165.0, 545.0),
+        ),
+        ("skadi.obj", 2.0, 240.0, Vec3::new(750.0, 50.0, 420.0)),
+        ("drone.obj", 30.0, 70.0, Vec3::new(780.0, 300.0, 550.0)),
+    ];
+    let start = Instant::now();
+    let loaded: Vec<_> = models
+        .into_par_iter()
+        .map(|(filename, scale, angle, offset)| (load_model(filename, scale), angle, offset))
+        .collect();
+    println!("模型加载耗时: {:.2?}", start.elapsed());
+    for (model, angle, offset) in loaded {
+        match model {
+            Ok(model) => {
+                let model = RotateY::new(Arc::new(model), angle);
+                world.add(Arc::new(Translate::new(Arc::new(model), offset)));
+            }
+            Err(err) => eprintln!("Warning: {}, skipping it.", err),
+        }
+    }
 
     // 玻璃砖
     let white = Dielectric::new(1.5);
@@ -664,13 +1115,13 @@ fn final_scene() {
     let mut rain = HittableList::new();
     for _i in 0..100 {
         let center: Vec3 = Vec3::new(
-            random_double_range(-500.0, 1055.0),
-            random_double_range(100.0, 555.0),
-            random_double_range(100.0, 855.0),
+            random_double_range(-500.0, 1055.0, &mut sampler),
+            random_double_range(100.0, 555.0, &mut sampler),
+            random_double_range(100.0, 855.0, &mut sampler),
         );
         let albedo = Color::new(0.73, 0.73, 0.73);
         let sphere_material = Lambertian::new(albedo);
-        let center2 = center + Vec3::new(0.0, random_double_range(20.0, 40.0), 0.0);
+        let center2 = center + Vec3::new(0.0, random_double_range(20.0, 40.0, &mut sampler), 0.0);
         rain.add(Arc::new(Sphere::new_dyn(
             center,
             center2,
@@ -678,7 +1129,7 @@ fn final_scene() {
             Arc::new(sphere_material),
         )));
     }
-    let rain = BvhNode::new_list(&mut rain);
+    let rain = Bvh::new_list(&rain);
     world.add(Arc::new(rain));
 
     // 右侧若干球
@@ -772,6 +1223,8 @@ fn final_scene() {
     cam.aspect_ratio = 16.0 / 9.0;
     cam.image_width = 1600;
     cam.samples_per_pixel = 5000;
+    cam.adaptive_threshold = 0.01;
+    cam.filter_type = FilterType::Mitchell;
     cam.max_depth = 50;
     cam.vfov = 40.0;
     cam.lookfrom = Vec3::new(278.0, 600.0, -600.0);
@@ -782,6 +1235,70 @@ fn final_scene() {
     cam.background = Color::new(0.01, 0.01, 0.01);
     let path = std::path::Path::new("output/final_scene_fixed.png");
     cam.initialize();
+    // 顶层约 30 个物体, 不加 BVH 时每条光线都要逐个求交
+    let world_arc: Arc<dyn Hittable> = Arc::new(Bvh::new_list(&world));
+    let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn gltf_test() {
+    // 相机和灯光都来自 glTF 文件
+    let scene = load_gltf("gltf_test.gltf", &SearchPaths::default())
+        .unwrap_or_else(|err| panic!("{}", err));
+    let mut cam = scene.camera.expect("glTF scene has no camera");
+    cam.image_width = 600;
+    cam.samples_per_pixel = 100;
+    let path = std::path::Path::new("output/test_gltf.png");
+    cam.initialize();
+    let world_arc: Arc<dyn Hittable> = Arc::new(scene.world);
+    let lights_arc: Arc<dyn Hittable> = Arc::new(scene.lights);
+    cam.render(&world_arc, &lights_arc, path);
+}
+fn ply_stl_test() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    // 顶点颜色来自 PLY 文件
+    let vertex_color = Arc::new(Lambertian::new_tex(Arc::new(VertexColor::new())));
+    world.add(Arc::new(Translate::new(
+        Arc::new(
+            load_ply("terrain.ply", 3.0, vertex_color.clone())
+                .unwrap_or_else(|err| panic!("{}", err)),
+        ),
+        Vec3::new(0.0, 0.0, 0.0),
+    )));
+    world.add(Arc::new(Translate::new(
+        Arc::new(
+            load_ply("icosphere.ply", 0.6, vertex_color).unwrap_or_else(|err| panic!("{}", err)),
+        ),
+        Vec3::new(-1.0, 1.1, 0.3),
+    )));
+    let steel = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.85), 0.1));
+    world.add(Arc::new(Translate::new(
+        Arc::new(load_stl("hex_nut.stl", 0.6, steel).unwrap_or_else(|err| panic!("{}", err))),
+        Vec3::new(1.0, 1.3, -0.2),
+    )));
+    let light = DiffuseLight::new(&Color::new(10.0, 10.0, 10.0));
+    let light_quad = Arc::new(Quad::new(
+        &Vec3::new(-1.0, 5.0, -1.0),
+        &Vec3::new(2.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 2.0),
+        Arc::new(light),
+    ));
+    world.add(light_quad.clone());
+    lights.add(light_quad);
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.vfov = 35.0;
+    cam.lookfrom = Vec3::new(0.0, 4.0, 7.0);
+    cam.lookat = Vec3::new(0.0, 0.5, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+    cam.focus_dist = 10.0;
+    cam.background = Color::new(0.2, 0.25, 0.35);
+    let path = std::path::Path::new("output/test_ply_stl.png");
+    cam.initialize();
     let world_arc: Arc<dyn Hittable> = Arc::new(world);
     let lights_arc: Arc<dyn Hittable> = Arc::new(lights);
     cam.render(&world_arc, &lights_arc, path);
@@ -794,8 +1311,17 @@ fn main() {
         1 => obj_test(),
         2 => normal_mapping_test(),
         3 => all_mapping_test(),
+        4 => absorbing_medium_test(),
+        5 => subsurface_test(),
+        6 => sdf_test(),
+        7 => primitives_test(),
+        8 => csg_test(),
         9 => book2_final_scene(800, 10000, 40),
         10 => book3_cornell_box(),
+        11 => transform_test(),
+        12 => instancing_test(),
+        13 => gltf_test(),
+        14 => ply_stl_test(),
         _ => (),
     }
     let duration = start.elapsed();
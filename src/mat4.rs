@@ -0,0 +1,181 @@
+use crate::utility::degrees_to_radians;
+use crate::vec3::Vec3;
+use std::ops::Mul;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Self {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+    pub fn translate(offset: &Vec3) -> Self {
+        let mut mat = Self::identity();
+        mat.m[0][3] = offset.x;
+        mat.m[1][3] = offset.y;
+        mat.m[2][3] = offset.z;
+        mat
+    }
+    pub fn scale(factor: &Vec3) -> Self {
+        let mut mat = Self::identity();
+        mat.m[0][0] = factor.x;
+        mat.m[1][1] = factor.y;
+        mat.m[2][2] = factor.z;
+        mat
+    }
+    pub fn rotate(axis: &Vec3, angle: f64) -> Self {
+        let a = axis.unit();
+        let radians = degrees_to_radians(angle);
+        let (sin_theta, cos_theta) = radians.sin_cos();
+        let t = 1.0 - cos_theta;
+        Self {
+            m: [
+                [
+                    t * a.x * a.x + cos_theta,
+                    t * a.x * a.y - sin_theta * a.z,
+                    t * a.x * a.z + sin_theta * a.y,
+                    0.0,
+                ],
+                [
+                    t * a.x * a.y + sin_theta * a.z,
+                    t * a.y * a.y + cos_theta,
+                    t * a.y * a.z - sin_theta * a.x,
+                    0.0,
+                ],
+                [
+                    t * a.x * a.z - sin_theta * a.y,
+                    t * a.y * a.z + sin_theta * a.x,
+                    t * a.z * a.z + cos_theta,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+    pub fn rotate_x(angle: f64) -> Self {
+        Self::rotate(&Vec3::new(1.0, 0.0, 0.0), angle)
+    }
+    pub fn rotate_y(angle: f64) -> Self {
+        Self::rotate(&Vec3::new(0.0, 1.0, 0.0), angle)
+    }
+    pub fn rotate_z(angle: f64) -> Self {
+        Self::rotate(&Vec3::new(0.0, 0.0, 1.0), angle)
+    }
+    pub fn transpose(&self) -> Self {
+        let mut mat = Self::identity();
+        for (i, row) in mat.m.iter_mut().enumerate() {
+            for (j, item) in row.iter_mut().enumerate() {
+                *item = self.m[j][i];
+            }
+        }
+        mat
+    }
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+    pub fn inverse(&self) -> Self {
+        let m = &self.m;
+        let inv_det = 1.0 / self.determinant3();
+        let mut inv = Self::identity();
+        inv.m[0][0] = (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det;
+        inv.m[0][1] = (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det;
+        inv.m[0][2] = (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det;
+        inv.m[1][0] = (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det;
+        inv.m[1][1] = (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det;
+        inv.m[1][2] = (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det;
+        inv.m[2][0] = (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det;
+        inv.m[2][1] = (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det;
+        inv.m[2][2] = (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det;
+        let t = Vec3::new(m[0][3], m[1][3], m[2][3]);
+        let inv_t = inv.transform_vector(&t);
+        inv.m[0][3] = -inv_t.x;
+        inv.m[1][3] = -inv_t.y;
+        inv.m[2][3] = -inv_t.z;
+        inv
+    }
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut mat = Self { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                mat.m[i][j] = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        mat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composite() -> Mat4 {
+        Mat4::translate(&Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotate(&Vec3::new(1.0, 2.0, -0.5), 37.0)
+            * Mat4::scale(&Vec3::new(2.0, 0.5, 3.0))
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let matrix = composite();
+        for product in [matrix * matrix.inverse(), matrix.inverse() * matrix] {
+            for i in 0..4 {
+                for j in 0..4 {
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((product.m[i][j] - expected).abs() < 1e-12, "{product:?}");
+                }
+            }
+        }
+        let p = Vec3::new(0.3, -4.0, 7.5);
+        let round_trip = matrix
+            .inverse()
+            .transform_point(&matrix.transform_point(&p));
+        assert!((round_trip - p).length() < 1e-12);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let matrix = composite();
+        let normal_matrix = matrix.inverse().transpose();
+        let n = Vec3::new(1.0, 1.0, 0.0).unit();
+        for tangent in [Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)] {
+            let t = matrix.transform_vector(&tangent);
+            let n = normal_matrix.transform_vector(&n);
+            assert!(n.dot(&t).abs() < 1e-12);
+        }
+        // 直接变换法线则会偏离切平面
+        assert!(
+            matrix
+                .transform_vector(&n)
+                .dot(&matrix.transform_vector(&Vec3::new(1.0, -1.0, 0.0)))
+                .abs()
+                > 0.1
+        );
+    }
+}
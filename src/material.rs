@@ -1,11 +1,14 @@
 use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::interval::Interval;
+use crate::medium::Medium;
+use crate::onb::Onb;
 use crate::pdf::{CosinePDF, Pdf, SpherePDF};
 use crate::ray::Ray;
 use crate::rtw_stb_image::RtwImage;
+use crate::sampler::Sampler;
 use crate::texture::{SolidColor, Texture};
-use crate::utility::{PI, random_double};
+use crate::utility::PI;
 use crate::vec3::Vec3;
 use dyn_clone::DynClone;
 use std::ops::Neg;
@@ -29,19 +32,43 @@ impl Default for ScatterRecord {
 }
 
 pub trait Material: DynClone + Send + Sync {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool;
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool;
     fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: &Vec3) -> Color {
         Color::new(0.0, 0.0, 0.0)
     }
     fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
         0.0
     }
-    fn get_normal(&self, _u: f64, _v: f64) -> Vec3 {
-        Vec3::new(0.0, 0.0, 0.0)
+    fn perturb_normal(&self, normal: &Vec3, _dpdu: &Vec3, _dpdv: &Vec3, _u: f64, _v: f64) -> Vec3 {
+        *normal
     }
     fn get_alpha(&self, _u: f64, _v: f64) -> f64 {
         1.0
     }
+    // 完全不透明时不消耗样本维度, 避免遍历中的图元数打乱后续维度
+    fn alpha_test(&self, u: f64, v: f64, sampler: &mut dyn Sampler) -> bool {
+        let alpha = self.get_alpha(u, v);
+        alpha >= 1.0 || sampler.get_1d() <= alpha
+    }
+    fn medium(&self) -> Option<&Medium> {
+        None
+    }
+    fn scatter_interface(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _eta: f64,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.scatter(r_in, rec, srec, sampler)
+    }
 }
 
 dyn_clone::clone_trait_object!(Material);
@@ -61,13 +88,22 @@ impl Lambertian {
     }
 }
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        srec.attenuation = self.tex.value_at(rec);
         srec.pdf_ptr = Arc::new(CosinePDF::new(&rec.normal));
         srec.skip_pdf = false;
         true
     }
     fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if rec.geometric_normal.dot(&scattered.direction) <= 0.0 {
+            return 0.0;
+        }
         let cos_theta = rec.normal.dot(&scattered.direction.unit());
         if cos_theta < 0.0 { 0.0 } else { cos_theta / PI }
     }
@@ -84,9 +120,15 @@ impl Metal {
     }
 }
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
         let mut reflected = Vec3::reflect(&r_in.direction, &rec.normal);
-        reflected = reflected.unit() + (Vec3::random_unit_vector() * self.fuzz);
+        reflected = reflected.unit() + (Vec3::random_unit_vector(sampler) * self.fuzz);
         srec.attenuation = self.albedo;
         srec.pdf_ptr = Arc::new(SpherePDF::new());
         srec.skip_pdf = true;
@@ -95,13 +137,91 @@ impl Material for Metal {
     }
 }
 
+#[derive(Clone)]
+pub struct Glossy {
+    albedo: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    metallic: Arc<dyn Texture>,
+}
+impl Glossy {
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        specular: Arc<dyn Texture>,
+        roughness: Arc<dyn Texture>,
+        metallic: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            albedo,
+            specular,
+            roughness,
+            metallic,
+        }
+    }
+}
+impl Material for Glossy {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let albedo = self.albedo.value_at(rec);
+        let metallic = Interval::new(0.0, 1.0).clamp(self.metallic.value_at(rec).x);
+        let roughness = Interval::new(0.0, 1.0).clamp(self.roughness.value_at(rec).x);
+        let specular = self.specular.value_at(rec) * (1.0 - metallic) + albedo * metallic;
+        let diffuse = albedo * (1.0 - metallic);
+        let specular_weight = specular.x + specular.y + specular.z;
+        let diffuse_weight = diffuse.x + diffuse.y + diffuse.z;
+        let specular_chance = if specular_weight + diffuse_weight > 0.0 {
+            specular_weight / (specular_weight + diffuse_weight)
+        } else {
+            0.0
+        };
+        if sampler.get_1d() < specular_chance {
+            let mut reflected = Vec3::reflect(&r_in.direction, &rec.normal);
+            reflected = reflected.unit() + (Vec3::random_unit_vector(sampler) * roughness);
+            if reflected.dot(&rec.geometric_normal) <= 0.0 {
+                return false;
+            }
+            srec.attenuation = specular / specular_chance;
+            srec.pdf_ptr = Arc::new(SpherePDF::new());
+            srec.skip_pdf = true;
+            srec.skip_pdf_ray = Ray::new_time(rec.p, reflected, r_in.tm);
+        } else {
+            srec.attenuation = diffuse / (1.0 - specular_chance);
+            srec.pdf_ptr = Arc::new(CosinePDF::new(&rec.normal));
+            srec.skip_pdf = false;
+        }
+        true
+    }
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if rec.geometric_normal.dot(&scattered.direction) <= 0.0 {
+            return 0.0;
+        }
+        let cos_theta = rec.normal.dot(&scattered.direction.unit());
+        if cos_theta < 0.0 { 0.0 } else { cos_theta / PI }
+    }
+}
+
 #[derive(Clone)]
 pub struct Dielectric {
     refraction_index: f64,
+    medium: Option<Medium>,
 }
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Dielectric {
-        Dielectric { refraction_index }
+        Dielectric {
+            refraction_index,
+            medium: None,
+        }
+    }
+    pub fn new_absorbing(refraction_index: f64, absorption: Color, priority: i32) -> Dielectric {
+        Dielectric {
+            refraction_index,
+            medium: Some(Medium::new(refraction_index, absorption, priority)),
+        }
     }
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
         let mut r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
@@ -110,20 +230,39 @@ impl Dielectric {
     }
 }
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = Color::new(1.0, 1.0, 1.0);
-        srec.pdf_ptr = Arc::new(SpherePDF::new());
-        srec.skip_pdf = true;
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
         let ri = if rec.front_face {
             1.0 / self.refraction_index
         } else {
             self.refraction_index
         };
+        self.scatter_interface(r_in, rec, srec, ri, sampler)
+    }
+    fn medium(&self) -> Option<&Medium> {
+        self.medium.as_ref()
+    }
+    fn scatter_interface(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        ri: f64,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.pdf_ptr = Arc::new(SpherePDF::new());
+        srec.skip_pdf = true;
         let unit_direction = r_in.direction.unit();
         let cos_theta = unit_direction.neg().dot(&rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > random_double() {
+        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > sampler.get_1d() {
             Vec3::reflect(&unit_direction, &rec.normal)
         } else {
             Vec3::refract(&unit_direction, &rec.normal, ri)
@@ -133,6 +272,51 @@ impl Material for Dielectric {
     }
 }
 
+#[derive(Clone)]
+pub struct Subsurface {
+    interface: Dielectric,
+}
+impl Subsurface {
+    pub fn new(albedo: Color, mean_free_path: Color, refraction_index: f64) -> Self {
+        Self {
+            interface: Dielectric {
+                refraction_index,
+                medium: Some(Medium::new_scattering(
+                    refraction_index,
+                    albedo,
+                    mean_free_path,
+                    0,
+                )),
+            },
+        }
+    }
+}
+impl Material for Subsurface {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.interface.scatter(r_in, rec, srec, sampler)
+    }
+    fn medium(&self) -> Option<&Medium> {
+        self.interface.medium()
+    }
+    fn scatter_interface(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        eta: f64,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.interface
+            .scatter_interface(r_in, rec, srec, eta, sampler)
+    }
+}
+
 #[derive(Clone)]
 pub struct DiffuseLight {
     tex: Arc<dyn Texture>,
@@ -149,7 +333,13 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
         false
     }
     fn emitted(&self, _r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Color {
@@ -160,6 +350,50 @@ impl Material for DiffuseLight {
     }
 }
 
+// glTF 聚光灯: 在内外锥角之间按规范的平方平滑衰减
+#[derive(Clone)]
+pub struct SpotLight {
+    emit: Color,
+    direction: Vec3,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+impl SpotLight {
+    pub fn new(
+        emit: &Color,
+        direction: Vec3,
+        inner_cone_angle: f64,
+        outer_cone_angle: f64,
+    ) -> Self {
+        Self {
+            emit: *emit,
+            direction: direction.unit(),
+            cos_inner: inner_cone_angle.cos(),
+            cos_outer: outer_cone_angle.cos(),
+        }
+    }
+}
+impl Material for SpotLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        false
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, _u: f64, _v: f64, _p: &Vec3) -> Color {
+        if !rec.front_face {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let cos_theta = -r_in.direction.unit().dot(&self.direction);
+        let scale = 1.0 / (self.cos_inner - self.cos_outer).max(0.001);
+        let attenuation = ((cos_theta - self.cos_outer) * scale).clamp(0.0, 1.0);
+        self.emit * (attenuation * attenuation)
+    }
+}
+
 #[derive(Clone)]
 pub struct Isotropic {
     tex: Arc<dyn Texture>,
@@ -175,8 +409,14 @@ impl Isotropic {
     // }
 }
 impl Material for Isotropic {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        srec.attenuation = self.tex.value_at(rec);
         srec.pdf_ptr = Arc::new(SpherePDF::new());
         srec.skip_pdf = false;
         true
@@ -190,7 +430,11 @@ impl Material for Isotropic {
 pub struct MappedMaterial {
     base_material: Arc<dyn Material>,
     normal_map: Option<Arc<RtwImage>>,
+    normal_scale: f64,
+    bump_map: Option<Arc<RtwImage>>,
+    bump_strength: f64,
     alpha_map: Option<Arc<RtwImage>>,
+    opacity: f64,
     light_map: Option<Arc<RtwImage>>,
     emissive_strength: f64,
 }
@@ -199,25 +443,58 @@ impl MappedMaterial {
         Self {
             base_material: base_material.clone(),
             normal_map: None,
+            normal_scale: 1.0,
+            bump_map: None,
+            bump_strength: 0.0,
             alpha_map: None,
+            opacity: 1.0,
             light_map: None,
             emissive_strength: 0.0,
         }
     }
     pub fn set_normal(&mut self, normal_filename: &str) {
-        self.normal_map = Option::from(Arc::new(RtwImage::new(normal_filename)));
+        self.set_normal_image(Arc::new(RtwImage::new(normal_filename)));
+    }
+    pub fn set_normal_image(&mut self, image: Arc<RtwImage>) {
+        self.normal_map = Option::from(image);
+    }
+    // 只缩放切线空间的 XY 分量 (glTF normalTexture.scale)
+    pub fn set_normal_scale(&mut self, normal_scale: f64) {
+        self.normal_scale = normal_scale;
+    }
+    pub fn set_bump(&mut self, bump_filename: &str, bump_strength: f64) {
+        self.set_bump_image(Arc::new(RtwImage::new(bump_filename)), bump_strength);
+    }
+    pub fn set_bump_image(&mut self, image: Arc<RtwImage>, bump_strength: f64) {
+        self.bump_map = Option::from(image);
+        self.bump_strength = bump_strength;
     }
     pub fn set_alpha(&mut self, alpha_filename: &str) {
-        self.alpha_map = Option::from(Arc::new(RtwImage::new(alpha_filename)));
+        self.set_alpha_image(Arc::new(RtwImage::new(alpha_filename)));
+    }
+    pub fn set_alpha_image(&mut self, image: Arc<RtwImage>) {
+        self.alpha_map = Option::from(image);
+    }
+    pub fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
     }
     pub fn set_light(&mut self, light_filename: &str, emissive_strength: f64) {
-        self.light_map = Option::from(Arc::new(RtwImage::new(light_filename)));
+        self.set_light_image(Arc::new(RtwImage::new(light_filename)), emissive_strength);
+    }
+    pub fn set_light_image(&mut self, image: Arc<RtwImage>, emissive_strength: f64) {
+        self.light_map = Option::from(image);
         self.emissive_strength = emissive_strength;
     }
 }
 impl Material for MappedMaterial {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        self.base_material.scatter(r_in, rec, srec)
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.base_material.scatter(r_in, rec, srec, sampler)
     }
     fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Vec3) -> Color {
         match &self.light_map {
@@ -240,21 +517,58 @@ impl Material for MappedMaterial {
     fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
         self.base_material.scattering_pdf(r_in, rec, scattered)
     }
-    fn get_normal(&self, u: f64, v: f64) -> Vec3 {
-        match &self.normal_map {
-            Some(image_data) => {
-                let u = Interval::new(0.0, 1.0).clamp(u);
-                let v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
-                let i = (image_data.image_width as f64 * u) as usize;
-                let j = (image_data.image_height as f64 * v) as usize;
-                let pixel = image_data.pixel_data(i, j);
-                Vec3::new(
-                    (pixel[0] as f64 / 255.99) * 2.0 - 1.0,
-                    (pixel[1] as f64 / 255.99) * 2.0 - 1.0,
-                    (pixel[2] as f64 / 255.99) * 2.0 - 1.0,
-                )
+    fn medium(&self) -> Option<&Medium> {
+        self.base_material.medium()
+    }
+    fn scatter_interface(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        eta: f64,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.base_material
+            .scatter_interface(r_in, rec, srec, eta, sampler)
+    }
+    fn perturb_normal(&self, normal: &Vec3, dpdu: &Vec3, dpdv: &Vec3, u: f64, v: f64) -> Vec3 {
+        if self.normal_map.is_none() && self.bump_map.is_none() {
+            return *normal;
+        }
+        let tangent = *dpdu - *normal * normal.dot(dpdu);
+        let (tangent, bitangent) = if tangent.near_zero() {
+            let uvw = Onb::new(normal);
+            (uvw.u(), uvw.v())
+        } else {
+            let tangent = tangent.unit();
+            let bitangent = normal.cross(&tangent);
+            if bitangent.dot(dpdv) < 0.0 {
+                (tangent, -bitangent)
+            } else {
+                (tangent, bitangent)
             }
-            None => Vec3::new(0.0, 0.0, 0.0),
+        };
+        let mut local = Vec3::new(0.0, 0.0, 1.0);
+        if let Some(image_data) = &self.bump_map {
+            let du = 1.0 / image_data.image_width.max(1) as f64;
+            let dv = 1.0 / image_data.image_height.max(1) as f64;
+            let height = |u: f64, v: f64| texel(image_data, u, v).x;
+            local.x = -(height(u + du, v) - height(u - du, v)) * self.bump_strength;
+            local.y = -(height(u, v + dv) - height(u, v - dv)) * self.bump_strength;
+        }
+        if let Some(image_data) = &self.normal_map {
+            let mapped = texel(image_data, u, v) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+            local = Vec3::new(
+                local.x + mapped.x * self.normal_scale,
+                local.y + mapped.y * self.normal_scale,
+                local.z * mapped.z,
+            );
+        }
+        let perturbed = tangent * local.x + bitangent * local.y + *normal * local.z;
+        if perturbed.near_zero() {
+            *normal
+        } else {
+            perturbed.unit()
         }
     }
     fn get_alpha(&self, u: f64, v: f64) -> f64 {
@@ -265,9 +579,22 @@ impl Material for MappedMaterial {
                 let i = (image_data.image_width as f64 * u) as usize;
                 let j = (image_data.image_height as f64 * v) as usize;
                 let pixel = image_data.pixel_data(i, j);
-                pixel[0] as f64 / 255.99
+                pixel[0] as f64 / 255.99 * self.opacity
             }
-            None => 1.0,
+            None => self.opacity,
         }
     }
 }
+
+fn texel(image_data: &RtwImage, u: f64, v: f64) -> Vec3 {
+    let u = Interval::new(0.0, 1.0).clamp(u);
+    let v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
+    let i = (image_data.image_width as f64 * u) as usize;
+    let j = (image_data.image_height as f64 * v) as usize;
+    let pixel = image_data.pixel_data(i, j);
+    Vec3::new(
+        pixel[0] as f64 / 255.99,
+        pixel[1] as f64 / 255.99,
+        pixel[2] as f64 / 255.99,
+    )
+}
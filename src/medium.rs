@@ -0,0 +1,238 @@
+use crate::color::Color;
+use crate::sampler::Sampler;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_MEDIUM_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy)]
+pub struct Medium {
+    id: usize,
+    pub ior: f64,
+    pub absorption: Color,
+    pub scattering: Color,
+    pub priority: i32,
+}
+impl Medium {
+    pub fn new(ior: f64, absorption: Color, priority: i32) -> Self {
+        Self {
+            id: NEXT_MEDIUM_ID.fetch_add(1, Ordering::Relaxed),
+            ior,
+            absorption,
+            scattering: Color::new(0.0, 0.0, 0.0),
+            priority,
+        }
+    }
+    pub fn new_scattering(ior: f64, albedo: Color, mean_free_path: Color, priority: i32) -> Self {
+        assert!(
+            [mean_free_path.x, mean_free_path.y, mean_free_path.z]
+                .iter()
+                .all(|d| *d > 0.0 && d.is_finite()),
+            "mean free path must be positive and finite, got {:?}",
+            mean_free_path
+        );
+        let extinction = Color::new(
+            1.0 / mean_free_path.x,
+            1.0 / mean_free_path.y,
+            1.0 / mean_free_path.z,
+        );
+        let scattering = albedo * extinction;
+        Self {
+            id: NEXT_MEDIUM_ID.fetch_add(1, Ordering::Relaxed),
+            ior,
+            absorption: extinction - scattering,
+            scattering,
+            priority,
+        }
+    }
+    fn extinction(&self) -> Color {
+        self.absorption + self.scattering
+    }
+    pub fn is_scattering(&self) -> bool {
+        self.scattering.x > 0.0 || self.scattering.y > 0.0 || self.scattering.z > 0.0
+    }
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let channel = |sigma_t: f64| {
+            if sigma_t <= 0.0 {
+                1.0
+            } else {
+                (-sigma_t * distance).exp()
+            }
+        };
+        let extinction = self.extinction();
+        Color::new(
+            channel(extinction.x),
+            channel(extinction.y),
+            channel(extinction.z),
+        )
+    }
+    pub fn sample_distance(
+        &self,
+        t_max: f64,
+        channel_weights: &Color,
+        sampler: &mut dyn Sampler,
+    ) -> (Option<f64>, Color) {
+        let extinction = self.extinction();
+        let weight_sum = channel_weights.x + channel_weights.y + channel_weights.z;
+        // 各通道权重下溢为 0 时无法构造 MIS 分母, 直接终止随机游走
+        if !(weight_sum > 0.0 && weight_sum.is_finite()) {
+            return (None, Color::new(0.0, 0.0, 0.0));
+        }
+        let channel_probs = *channel_weights / weight_sum;
+        let xi = sampler.get_1d();
+        let channel = if xi < channel_probs.x {
+            0
+        } else if xi < channel_probs.x + channel_probs.y {
+            1
+        } else {
+            2
+        };
+        let distance = -(1.0 - sampler.get_1d()).ln() / extinction.axis(channel);
+        let scattered = distance < t_max;
+        let distance = distance.min(t_max);
+        let transmittance = self.transmittance(distance);
+        let density = if scattered {
+            extinction * transmittance
+        } else {
+            transmittance
+        };
+        let pdf = channel_probs.dot(&density);
+        if !(pdf > 0.0 && pdf.is_finite()) {
+            return (None, Color::new(0.0, 0.0, 0.0));
+        }
+        if scattered {
+            (Some(distance), self.scattering * transmittance / pdf)
+        } else {
+            (None, transmittance / pdf)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MediumStack {
+    media: Vec<Medium>,
+    walk_weight: Color,
+}
+impl MediumStack {
+    pub fn new() -> Self {
+        Self {
+            media: Vec::new(),
+            walk_weight: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+    pub fn current(&self) -> Option<&Medium> {
+        self.media.iter().max_by_key(|m| m.priority)
+    }
+    pub fn current_ior(&self) -> f64 {
+        self.current().map_or(1.0, |m| m.ior)
+    }
+    pub fn transmittance(&self, distance: f64) -> Color {
+        match self.current() {
+            Some(medium) => medium.transmittance(distance),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+    pub fn sample_distance(
+        &self,
+        t_max: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Option<f64>, Color)> {
+        self.current()
+            .filter(|medium| medium.is_scattering())
+            .map(|medium| medium.sample_distance(t_max, &self.walk_weight, sampler))
+    }
+    pub fn scattered(&self, weight: &Color) -> Self {
+        let mut next = self.clone();
+        next.walk_weight = next.walk_weight * *weight;
+        next
+    }
+    pub fn is_false_hit(&self, medium: &Medium) -> bool {
+        match self
+            .media
+            .iter()
+            .filter(|m| m.id != medium.id)
+            .map(|m| m.priority)
+            .max()
+        {
+            Some(priority) => medium.priority < priority,
+            None => false,
+        }
+    }
+    fn push(&mut self, medium: Medium) {
+        self.media.push(medium);
+    }
+    fn remove(&mut self, medium: &Medium) {
+        if let Some(index) = self.media.iter().rposition(|m| m.id == medium.id) {
+            self.media.remove(index);
+        }
+    }
+    pub fn cross(&mut self, medium: &Medium, entering: bool) {
+        if entering {
+            self.push(*medium);
+        } else {
+            self.remove(medium);
+        }
+    }
+    pub fn eta(&self, medium: &Medium, entering: bool) -> f64 {
+        if entering {
+            self.current_ior() / medium.ior
+        } else {
+            let mut outside = self.clone();
+            outside.remove(medium);
+            medium.ior / outside.current_ior()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beer_lambert_absorption_is_exponential() {
+        let absorption = Color::new(0.1, 0.5, 2.0);
+        let medium = Medium::new(1.33, absorption, 0);
+        let t = medium.transmittance(3.0);
+        assert!((t.x - (-0.3f64).exp()).abs() < 1e-12);
+        assert!((t.y - (-1.5f64).exp()).abs() < 1e-12);
+        assert!((t.z - (-6.0f64).exp()).abs() < 1e-12);
+        // 距离可加: T(a + b) = T(a) T(b)
+        let product = medium.transmittance(1.0) * medium.transmittance(2.0);
+        assert!((product - t).length() < 1e-12);
+        let mut stack = MediumStack::new();
+        assert_eq!(stack.transmittance(3.0).x, 1.0);
+        stack.cross(&medium, true);
+        assert_eq!(stack.transmittance(3.0).z, t.z);
+    }
+
+    #[test]
+    fn glass_of_water_follows_priorities() {
+        // 玻璃壁与水体重叠, 玻璃优先级更高
+        let glass = Medium::new(1.5, Color::new(0.0, 0.0, 0.0), 2);
+        let water = Medium::new(1.33, Color::new(0.0, 0.0, 0.0), 1);
+        let mut stack = MediumStack::new();
+
+        // 空气 -> 玻璃外壁
+        assert!(!stack.is_false_hit(&glass));
+        assert_eq!(stack.eta(&glass, true), 1.0 / 1.5);
+        stack.cross(&glass, true);
+        assert_eq!(stack.current_ior(), 1.5);
+
+        // 玻璃壁内的水面是假命中: 压栈但当前介质仍是玻璃
+        assert!(stack.is_false_hit(&water));
+        stack.cross(&water, true);
+        assert_eq!(stack.current_ior(), 1.5);
+
+        // 玻璃内壁 -> 水
+        assert!(!stack.is_false_hit(&glass));
+        assert_eq!(stack.eta(&glass, false), 1.5 / 1.33);
+        stack.cross(&glass, false);
+        assert_eq!(stack.current_ior(), 1.33);
+
+        // 水面 -> 空气
+        assert!(!stack.is_false_hit(&water));
+        assert_eq!(stack.eta(&water, false), 1.33);
+        stack.cross(&water, false);
+        assert!(stack.current().is_none());
+        assert_eq!(stack.current_ior(), 1.0);
+    }
+}
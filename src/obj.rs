@@ -1,16 +1,33 @@
-use crate::bvh;
+use crate::asset::{LoadError, SearchPaths};
 use crate::color::Color;
+use crate::hittable::Hittable;
 use crate::hittable_list::HittableList;
-use crate::material::{Lambertian, Material};
-use crate::texture::{ImageTexture, SolidColor, Texture, UV};
-use crate::triangle::Triangle;
+use crate::material::{Dielectric, DiffuseLight, Glossy, Lambertian, MappedMaterial, Material};
+use crate::rtw_stb_image::RtwImage;
+use crate::texture::{ChannelTexture, ImageTexture, SolidColor, Texture};
+use crate::triangle::TriangleMesh;
 use crate::vec3::Vec3;
 use std::path::Path;
 use std::sync::Arc;
 
-pub fn load_model(obj_filename: &str, scale: f64) -> HittableList {
-    let mut world = HittableList::new();
-    let obj_path = format!("assets/{}", obj_filename);
+pub fn load_model(obj_filename: &str, scale: f64) -> Result<HittableList, LoadError> {
+    let mut final_world = HittableList::new();
+    final_world.add(load_blas(obj_filename, scale)?);
+    Ok(final_world)
+}
+
+pub fn load_blas(obj_filename: &str, scale: f64) -> Result<Arc<dyn Hittable>, LoadError> {
+    load_blas_from(obj_filename, scale, &SearchPaths::default())
+}
+
+pub fn load_blas_from(
+    obj_filename: &str,
+    scale: f64,
+    search_paths: &SearchPaths,
+) -> Result<Arc<dyn Hittable>, LoadError> {
+    let obj_path = search_paths
+        .resolve(obj_filename)
+        .ok_or_else(|| LoadError::MissingFile(obj_filename.to_string()))?;
     let (models, materials) = tobj::load_obj(
         &obj_path,
         &tobj::LoadOptions {
@@ -20,82 +37,491 @@ pub fn load_model(obj_filename: &str, scale: f64) -> HittableList {
             ignore_lines: false,
         },
     )
-    .expect("Failed to load OBJ file");
-    let model_dir = Path::new(&obj_path)
-        .parent()
-        .expect("Failed to get model directory");
-    let mut loaded_materials: Vec<Arc<dyn Material>> = Vec::new();
-    if let Ok(materials) = materials {
-        println!("Found {} materials in .mtl file.", materials.len());
-        for m in materials {
-            let albedo: Arc<dyn Texture> = if let Some(texture_name) = &m.diffuse_texture {
-                let texture_path = model_dir.join(texture_name);
-                println!("Loading texture: {:?}", texture_path);
-                Arc::new(ImageTexture::new(texture_path.to_str().unwrap()))
-            } else {
-                let diffuse = m.diffuse.unwrap_or([0.73, 0.73, 0.73]);
-                let color = Color::new(diffuse[0] as f64, diffuse[1] as f64, diffuse[2] as f64);
-                Arc::new(SolidColor::new_color(&color))
-            };
-            loaded_materials.push(Arc::new(Lambertian::new_tex(albedo)));
+    .map_err(|err| LoadError::Parse {
+        path: obj_path.clone(),
+        message: err.to_string(),
+    })?;
+    // 找不到 .mtl 时所有面退回默认材质; .mtl 存在但无法解析时报错
+    let materials = match materials {
+        Ok(materials) => materials,
+        Err(tobj::LoadError::OpenFileFailed) => {
+            eprintln!(
+                "Warning: materials of {:?} not found, using the default material.",
+                obj_path
+            );
+            Vec::new()
         }
-    }
+        Err(err) => {
+            return Err(LoadError::BadMtl {
+                path: obj_path.clone(),
+                message: err.to_string(),
+            });
+        }
+    };
+    let model_dir = obj_path.parent().unwrap_or(Path::new(""));
+    let texture_paths = search_paths.relative_to(model_dir);
+    println!("Found {} materials in .mtl file.", materials.len());
+    let mut loaded_materials: Vec<Arc<dyn Material>> = materials
+        .iter()
+        .map(|m| load_material(m, &texture_paths))
+        .collect();
     let default_material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.0, 0.8)));
+    let default_index = loaded_materials.len() as u32;
+    loaded_materials.push(default_material);
+    let mut triangle_mesh = TriangleMesh::new();
+    let has_texcoords = models.iter().any(|model| !model.mesh.texcoords.is_empty());
+    let has_normals = models.iter().all(|model| !model.mesh.normals.is_empty());
     println!("Loading {} models...", models.len());
     for model in models {
         let mesh = &model.mesh;
         println!("Loading {} models...", mesh.indices.len());
-        for f in 0..mesh.indices.len() / 3 {
-            let i0 = mesh.indices[3 * f] as usize;
-            let i1 = mesh.indices[3 * f + 1] as usize;
-            let i2 = mesh.indices[3 * f + 2] as usize;
-            let p0 = Vec3::new(
-                mesh.positions[3 * i0] as f64,
-                mesh.positions[3 * i0 + 1] as f64,
-                mesh.positions[3 * i0 + 2] as f64,
-            ) * scale;
-            let p1 = Vec3::new(
-                mesh.positions[3 * i1] as f64,
-                mesh.positions[3 * i1 + 1] as f64,
-                mesh.positions[3 * i1 + 2] as f64,
-            ) * scale;
-            let p2 = Vec3::new(
-                mesh.positions[3 * i2] as f64,
-                mesh.positions[3 * i2 + 1] as f64,
-                mesh.positions[3 * i2 + 2] as f64,
-            ) * scale;
-            let uvs = if !mesh.texcoords.is_empty() {
-                UV::new(
-                    Vec3::new(
-                        mesh.texcoords[2 * i0] as f64,
-                        mesh.texcoords[2 * i1] as f64,
-                        mesh.texcoords[2 * i2] as f64,
-                    ),
-                    Vec3::new(
-                        mesh.texcoords[2 * i0 + 1] as f64,
-                        mesh.texcoords[2 * i1 + 1] as f64,
-                        mesh.texcoords[2 * i2 + 1] as f64,
-                    ),
-                )
+        let base = triangle_mesh.positions.len() as u32;
+        for p in mesh.positions.chunks_exact(3) {
+            triangle_mesh
+                .positions
+                .push(Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64) * scale);
+        }
+        if has_texcoords {
+            let vertex_count = mesh.positions.len() / 3;
+            for k in 0..vertex_count {
+                triangle_mesh.texcoords.push(if mesh.texcoords.is_empty() {
+                    [0.0, 0.0]
+                } else {
+                    [
+                        mesh.texcoords[2 * k] as f64,
+                        mesh.texcoords[2 * k + 1] as f64,
+                    ]
+                });
+            }
+        }
+        if has_normals {
+            for n in mesh.normals.chunks_exact(3) {
+                triangle_mesh
+                    .normals
+                    .push(Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64));
+            }
+        }
+        let material_index = match mesh.material_id {
+            Some(mat_id) if mat_id < materials.len() => mat_id as u32,
+            Some(mat_id) => {
+                return Err(LoadError::MaterialOutOfRange {
+                    material: mat_id,
+                    count: materials.len(),
+                });
+            }
+            None => default_index,
+        };
+        for f in mesh.indices.chunks_exact(3) {
+            triangle_mesh
+                .indices
+                .push([base + f[0], base + f[1], base + f[2]]);
+            triangle_mesh.face_materials.push(material_index);
+        }
+    }
+    triangle_mesh.materials = loaded_materials;
+    println!("done model loading!");
+    Ok(triangle_mesh.build_bvh())
+}
+
+fn load_material(m: &tobj::Material, texture_paths: &SearchPaths) -> Arc<dyn Material> {
+    let param = |key: &str| m.unknown_param.get(key);
+    let param_value = |key: &str| {
+        param(key)
+            .and_then(|value| value.split_whitespace().next())
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    let albedo: Arc<dyn Texture> = match &m.diffuse_texture {
+        Some(spec) => image_texture(texture_paths, spec),
+        None => solid_texture(to_color(m.diffuse, [0.73, 0.73, 0.73])),
+    };
+    let specular = to_color(m.specular, [0.0, 0.0, 0.0]);
+    let emission = to_color(m.emissive, [0.0, 0.0, 0.0]);
+    let illum = m.illumination_model.unwrap_or(2);
+    let is_glass = matches!(illum, 4 | 6 | 7 | 9);
+    let is_pbr = ["Pr", "Pm", "map_Pr", "map_Pm"]
+        .iter()
+        .any(|key| param(key).is_some());
+    let mut base: Arc<dyn Material> = if is_glass {
+        Arc::new(Dielectric::new(m.optical_density.unwrap_or(1.5) as f64))
+    } else if is_pbr {
+        let roughness = match param("map_Pr") {
+            Some(spec) => data_texture(texture_paths, spec),
+            None => solid_value(param_value("Pr").unwrap_or(0.5)),
+        };
+        let metallic = match param("map_Pm") {
+            Some(spec) => data_texture(texture_paths, spec),
+            None => solid_value(param_value("Pm").unwrap_or(0.0)),
+        };
+        Arc::new(Glossy::new(albedo, solid_value(0.04), roughness, metallic))
+    } else if illum >= 2 && (m.specular_texture.is_some() || !specular.near_zero()) {
+        let specular = match &m.specular_texture {
+            Some(spec) => image_texture(texture_paths, spec),
+            None => solid_texture(specular),
+        };
+        let shininess = m.shininess.unwrap_or(0.0).max(0.0) as f64;
+        let roughness = solid_value((2.0 / (shininess + 2.0)).sqrt());
+        Arc::new(Glossy::new(albedo, specular, roughness, solid_value(0.0)))
+    } else {
+        Arc::new(Lambertian::new_tex(albedo))
+    };
+    if param("map_Ke").is_none() && !emission.near_zero() {
+        base = Arc::new(DiffuseLight::new(&emission));
+    }
+    let opacity = if is_glass {
+        1.0
+    } else {
+        m.dissolve
+            .map(|d| d as f64)
+            .or(param_value("Tr").map(|tr| 1.0 - tr))
+            .unwrap_or(1.0)
+    };
+    let normal_map = param("norm");
+    if normal_map.is_none()
+        && m.normal_texture.is_none()
+        && m.dissolve_texture.is_none()
+        && param("map_Ke").is_none()
+        && opacity >= 1.0
+    {
+        return base;
+    }
+    let mut mapped = MappedMaterial::new(base);
+    if let Some(image) = normal_map.and_then(|spec| load_image(texture_paths, spec)) {
+        mapped.set_normal_image(image);
+    }
+    if let Some(spec) = &m.normal_texture {
+        let (_, bump_multiplier) = texture_option(spec);
+        if let Some(image) = load_image(texture_paths, spec) {
+            if looks_like_normal_map(&image) {
+                mapped.set_normal_image(image);
             } else {
-                UV::default()
-            };
-            let mat = match mesh.material_id {
-                Some(mat_id) => loaded_materials[mat_id].clone(),
-                None => default_material.clone(),
+                mapped.set_bump_image(image, bump_multiplier);
+            }
+        }
+    }
+    if let Some(image) = m
+        .dissolve_texture
+        .as_ref()
+        .and_then(|spec| load_image(texture_paths, spec))
+    {
+        mapped.set_alpha_image(image);
+    }
+    if opacity < 1.0 {
+        mapped.set_opacity(opacity);
+    }
+    if let Some(image) = param("map_Ke").and_then(|spec| load_image(texture_paths, spec)) {
+        let strength = emission.x.max(emission.y).max(emission.z);
+        mapped.set_light_image(image, if strength > 0.0 { strength } else { 1.0 });
+    }
+    Arc::new(mapped)
+}
+
+fn to_color(value: Option<[f32; 3]>, default: [f32; 3]) -> Color {
+    let c = value.unwrap_or(default);
+    Color::new(c[0] as f64, c[1] as f64, c[2] as f64)
+}
+
+fn solid_texture(color: Color) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new_color(&color))
+}
+
+fn solid_value(value: f64) -> Arc<dyn Texture> {
+    solid_texture(Color::new(value, value, value))
+}
+
+fn image_texture(texture_paths: &SearchPaths, spec: &str) -> Arc<dyn Texture> {
+    match load_image(texture_paths, spec) {
+        Some(image) => Arc::new(ImageTexture::from_image(image, Color::new(1.0, 1.0, 1.0))),
+        None => solid_texture(Color::new(1.0, 0.0, 1.0)),
+    }
+}
+
+// 粗糙度, 金属度等数据贴图按线性值读取单通道, 不做 gamma 解码
+fn data_texture(texture_paths: &SearchPaths, spec: &str) -> Arc<dyn Texture> {
+    match load_image(texture_paths, spec) {
+        Some(image) => Arc::new(ChannelTexture::new(image, 0, 1.0)),
+        None => solid_value(0.5),
+    }
+}
+
+fn load_image(texture_paths: &SearchPaths, spec: &str) -> Option<Arc<RtwImage>> {
+    let (filename, _) = texture_option(spec);
+    println!("Loading texture: {}", filename);
+    match RtwImage::open(&filename, texture_paths) {
+        Ok(image) => Some(Arc::new(image)),
+        Err(err) => {
+            eprintln!("Warning: {}, using a placeholder.", err);
+            None
+        }
+    }
+}
+
+fn texture_option(spec: &str) -> (String, f64) {
+    let mut bump_multiplier = 1.0;
+    let mut tokens = spec.split_whitespace().peekable();
+    while let Some(option) = tokens.next_if(|token| token.starts_with('-')) {
+        match option {
+            "-o" | "-s" | "-t" | "-mm" => {
+                for _ in 0..3 {
+                    if tokens
+                        .next_if(|token| token.parse::<f64>().is_ok())
+                        .is_none()
+                    {
+                        break;
+                    }
+                }
+            }
+            "-bm" => {
+                if let Some(value) = tokens.next().and_then(|token| token.parse().ok()) {
+                    bump_multiplier = value;
+                }
+            }
+            _ => {
+                tokens.next();
+            }
+        }
+    }
+    (tokens.collect::<Vec<_>>().join(" "), bump_multiplier)
+}
+
+fn looks_like_normal_map(image: &RtwImage) -> bool {
+    let mut mean = Color::default();
+    let steps = 16;
+    for j in 0..steps {
+        for i in 0..steps {
+            let pixel = image.pixel_data(
+                i * image.image_width / steps,
+                j * image.image_height / steps,
+            );
+            mean = mean + Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64) / 255.0;
+        }
+    }
+    mean = mean / (steps * steps) as f64;
+    mean.z > 0.6 && (mean.x - 0.5).abs() < 0.15 && (mean.y - 0.5).abs() < 0.15
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::material::ScatterRecord;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn mtl(fields: &[(&str, &str)]) -> Arc<dyn Material> {
+        let mut m = tobj::Material {
+            diffuse: Some([0.2, 0.4, 0.6]),
+            ..tobj::Material::default()
+        };
+        for &(key, value) in fields {
+            let rgb = || {
+                let v: Vec<f32> = value
+                    .split_whitespace()
+                    .map(|x| x.parse().unwrap())
+                    .collect();
+                Some([v[0], v[1], v[2]])
             };
-            world.add(Arc::new(Triangle::new(
-                &p0,
-                &(p1 - p0),
-                &(p2 - p0),
-                mat,
-                uvs,
-            )));
+            match key {
+                "Kd" => m.diffuse = rgb(),
+                "Ks" => m.specular = rgb(),
+                "Ke" => m.emissive = rgb(),
+                "Ns" => m.shininess = value.parse().ok(),
+                "d" => m.dissolve = value.parse().ok(),
+                "Ni" => m.optical_density = value.parse().ok(),
+                "illum" => m.illumination_model = value.parse().ok(),
+                _ => {
+                    m.unknown_param.insert(key.to_string(), value.to_string());
+                }
+            }
         }
+        load_material(&m, &SearchPaths::new())
+    }
+
+    // 从正上方照射朝 +z 的表面
+    fn scatter(mat: &Arc<dyn Material>) -> Option<ScatterRecord> {
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            geometric_normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            mat: mat.clone(),
+            ..HitRecord::default()
+        };
+        let r = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut srec = ScatterRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        mat.scatter(&r, &rec, &mut srec, &mut sampler)
+            .then_some(srec)
+    }
+
+    fn assert_color(actual: Color, expected: Color) {
+        assert!(
+            (actual - expected).length() < 1e-6,
+            "{actual:?} vs {expected:?}"
+        );
+    }
+
+    #[test]
+    fn diffuse_only_is_lambertian() {
+        let srec = scatter(&mtl(&[])).unwrap();
+        assert!(!srec.skip_pdf);
+        assert_color(srec.attenuation, Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn specular_exponent_controls_glossiness() {
+        let srec = scatter(&mtl(&[("Kd", "0 0 0"), ("Ks", "1 1 1"), ("Ns", "1000000")])).unwrap();
+        assert!(srec.skip_pdf);
+        let reflected = srec.skip_pdf_ray.direction.unit();
+        assert!(reflected.z > 0.999, "{reflected:?}");
+    }
+
+    #[test]
+    fn pbr_metallic_reflects_the_base_colour() {
+        let srec = scatter(&mtl(&[("Pm", "1"), ("Pr", "0")])).unwrap();
+        assert!(srec.skip_pdf);
+        assert_color(srec.attenuation, Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn glass_illumination_models_refract() {
+        for illum in ["4", "6", "7", "9"] {
+            let srec = scatter(&mtl(&[("illum", illum), ("Ni", "1.5")])).unwrap();
+            assert!(srec.skip_pdf, "illum {illum}");
+        }
+    }
+
+    #[test]
+    fn emission_and_dissolve_are_applied() {
+        let light = mtl(&[("Ke", "4 2 1")]);
+        assert!(scatter(&light).is_none());
+        let rec = HitRecord {
+            front_face: true,
+            ..HitRecord::default()
+        };
+        let r = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_color(
+            light.emitted(&r, &rec, 0.0, 0.0, &rec.p),
+            Color::new(4.0, 2.0, 1.0),
+        );
+        assert_eq!(mtl(&[("d", "0.25")]).get_alpha(0.5, 0.5), 0.25);
+        assert_eq!(mtl(&[("Tr", "0.75")]).get_alpha(0.5, 0.5), 0.25);
+        assert_eq!(mtl(&[]).get_alpha(0.5, 0.5), 1.0);
+    }
+
+    #[test]
+    fn data_maps_are_read_linearly() {
+        let dir = test_dir("data-map");
+        image::GrayImage::from_pixel(2, 2, image::Luma([128]))
+            .save(dir.join("rough.png"))
+            .unwrap();
+        let mut paths = SearchPaths::new();
+        paths.add(&dir);
+        let texture = data_texture(&paths, "rough.png");
+        assert!((texture.value(0.5, 0.5, &Vec3::default()).x - 128.0 / 255.0).abs() < 1e-9);
+        // 缺失的贴图退回中间值
+        let missing = data_texture(&paths, "missing.png");
+        assert_eq!(missing.value(0.5, 0.5, &Vec3::default()).x, 0.5);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn texture_options_are_stripped() {
+        assert_eq!(
+            texture_option("-bm 0.5 bump.png"),
+            ("bump.png".to_string(), 0.5)
+        );
+        assert_eq!(
+            texture_option("-s 2 2 1 -o 0.5 0.5 -clamp on my texture.png"),
+            ("my texture.png".to_string(), 1.0)
+        );
+    }
+
+    const QUAD_OBJ: &str = "mtllib quad.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+usemtl glow
+f 1 2 3 4
+";
+
+    #[test]
+    fn obj_and_mtl_resolve_relative_to_the_model() {
+        let dir = test_dir("obj-model");
+        std::fs::write(dir.join("quad.obj"), QUAD_OBJ).unwrap();
+        std::fs::write(dir.join("quad.mtl"), "newmtl glow\nKe 3 2 1\n").unwrap();
+        let mut paths = SearchPaths::new();
+        paths.add(&dir);
+        let blas = load_blas_from("quad.obj", 2.0, &paths).unwrap();
+        let r = Ray::new(Vec3::new(1.5, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(blas.hit(
+            &r,
+            &crate::interval::Interval::new(0.001, f64::MAX),
+            &mut rec,
+            &mut sampler
+        ));
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert_color(
+            rec.mat.emitted(&r, &rec, rec.u, rec.v, &rec.p),
+            Color::new(3.0, 2.0, 1.0),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_mtllib_falls_back_to_the_default_material() {
+        let dir = test_dir("obj-no-mtl");
+        // mtllib 指向不存在的文件
+        std::fs::write(dir.join("no_mtl.obj"), QUAD_OBJ).unwrap();
+        let mut paths = SearchPaths::new();
+        paths.add(&dir);
+        let blas = load_blas_from("no_mtl.obj", 1.0, &paths).unwrap();
+        let r = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(blas.hit(
+            &r,
+            &crate::interval::Interval::new(0.001, f64::MAX),
+            &mut rec,
+            &mut sampler
+        ));
+        assert_color(
+            scatter(&rec.mat).unwrap().attenuation,
+            Color::new(0.8, 0.0, 0.8),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn obj_failures_are_load_errors() {
+        let dir = test_dir("obj-errors");
+        let mut paths = SearchPaths::new();
+        paths.add(&dir);
+        let err = load_blas_from("missing.obj", 1.0, &paths).err().unwrap();
+        assert!(matches!(err, LoadError::MissingFile(ref name) if name == "missing.obj"));
+        // mtllib 指向的文件存在但无法解析
+        std::fs::write(
+            dir.join("bad_mtl.obj"),
+            QUAD_OBJ.replace("quad.mtl", "bad.mtl"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("bad.mtl"), "newmtl glow\nNs shiny\n").unwrap();
+        let err = load_blas_from("bad_mtl.obj", 1.0, &paths).err().unwrap();
+        assert!(matches!(err, LoadError::BadMtl { .. }), "{err}");
+        // 面索引越界
+        std::fs::write(dir.join("bad_face.obj"), "v 0 0 0\nv 1 0 0\nf 1 2 9\n").unwrap();
+        let err = load_blas_from("bad_face.obj", 1.0, &paths).err().unwrap();
+        assert!(
+            matches!(err, LoadError::Parse { ref path, .. } if path.ends_with("bad_face.obj")),
+            "{err}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-    println!("done model loading!");
-    let bvh_node = bvh::BvhNode::new_list(&mut world);
-    let mut final_world = HittableList::new();
-    final_world.add(Arc::new(bvh_node));
-    final_world
 }
@@ -16,12 +16,12 @@ impl Onb {
         let x = z.cross(&y);
         Self { axis: [x, y, z] }
     }
-    // pub fn u(&self) -> Vec3 {
-    //     self.axis[0]
-    // }
-    // pub fn v(&self) -> Vec3 {
-    //     self.axis[1]
-    // }
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
     pub fn w(&self) -> Vec3 {
         self.axis[2]
     }
@@ -0,0 +1,82 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utility::{PI, solve_quadratic};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Paraboloid {
+    center: Vec3,
+    radius: f64,
+    height: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+impl Paraboloid {
+    pub fn new(center: Vec3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vec3::new(radius, 0.0, radius);
+        Self {
+            center,
+            radius,
+            height,
+            mat,
+            bbox: Aabb::new_points(
+                &(center - rvec),
+                &(center + rvec + Vec3::new(0.0, height, 0.0)),
+            ),
+        }
+    }
+}
+impl Paraboloid {
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Vec3)> {
+        let o = r.origin - self.center;
+        let d = r.direction;
+        let k = self.height / (self.radius * self.radius);
+        let (t0, t1) = solve_quadratic(
+            k * (d.x * d.x + d.z * d.z),
+            2.0 * k * (o.x * d.x + o.z * d.z) - d.y,
+            k * (o.x * o.x + o.z * o.z) - o.y,
+        )?;
+        [t0, t1]
+            .into_iter()
+            .filter(|t| ray_t.surrounds(*t))
+            .map(|t| (t, o + d * t))
+            .find(|(_, p)| p.y >= 0.0 && p.y <= self.height)
+    }
+    fn get_uv(&self, p: &Vec3) -> (f64, f64) {
+        (((-p.z).atan2(p.x) + PI) / (2.0 * PI), p.y / self.height)
+    }
+}
+impl Hittable for Paraboloid {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, p)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        let k = self.height / (self.radius * self.radius);
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = Vec3::new(2.0 * k * p.x, -1.0, 2.0 * k * p.z).unit();
+        (rec.u, rec.v) = self.get_uv(&p);
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, outward_normal, rec.u, rec.v);
+        rec.mat.alpha_test(rec.u, rec.v, sampler)
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t).is_some_and(|(_, p)| {
+            let (u, v) = self.get_uv(&p);
+            self.mat.alpha_test(u, v, sampler)
+        })
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -1,12 +1,13 @@
 use crate::hittable::Hittable;
 use crate::onb::Onb;
-use crate::utility::{PI, random_double};
+use crate::sampler::Sampler;
+use crate::utility::PI;
 use crate::vec3::{Vec3, random_cosine_direction};
 use std::sync::Arc;
 
 pub trait Pdf {
-    fn value(&self, direction: &Vec3) -> f64;
-    fn generate(&self) -> Vec3;
+    fn value(&self, direction: &Vec3, sampler: &mut dyn Sampler) -> f64;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
 }
 
 pub struct SpherePDF {}
@@ -16,11 +17,11 @@ impl SpherePDF {
     }
 }
 impl Pdf for SpherePDF {
-    fn value(&self, _direction: &Vec3) -> f64 {
+    fn value(&self, _direction: &Vec3, _sampler: &mut dyn Sampler) -> f64 {
         1.0 / (4.0 * PI)
     }
-    fn generate(&self) -> Vec3 {
-        Vec3::random_unit_vector()
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::random_unit_vector(sampler)
     }
 }
 
@@ -33,12 +34,12 @@ impl CosinePDF {
     }
 }
 impl Pdf for CosinePDF {
-    fn value(&self, direction: &Vec3) -> f64 {
+    fn value(&self, direction: &Vec3, _sampler: &mut dyn Sampler) -> f64 {
         let cosine_theta = direction.unit().dot(&self.uvw.w());
         (cosine_theta / PI).max(0.0)
     }
-    fn generate(&self) -> Vec3 {
-        self.uvw.transform(&random_cosine_direction())
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.transform(&random_cosine_direction(sampler))
     }
 }
 
@@ -55,12 +56,12 @@ impl HittablePDF {
     }
 }
 impl Pdf for HittablePDF {
-    fn value(&self, direction: &Vec3) -> f64 {
-        self.objects.pdf_value(&self.origin, direction)
+    fn value(&self, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        self.objects.pdf_value(&self.origin, direction, sampler)
     }
 
-    fn generate(&self) -> Vec3 {
-        self.objects.random(&self.origin)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.objects.random(&self.origin, sampler)
     }
 }
 
@@ -73,15 +74,15 @@ impl MixturePDF {
     }
 }
 impl Pdf for MixturePDF {
-    fn value(&self, direction: &Vec3) -> f64 {
-        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    fn value(&self, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
+        0.5 * self.p[0].value(direction, sampler) + 0.5 * self.p[1].value(direction, sampler)
     }
 
-    fn generate(&self) -> Vec3 {
-        if random_double() < 0.5 {
-            self.p[0].generate()
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < 0.5 {
+            self.p[0].generate(sampler)
         } else {
-            self.p[1].generate()
+            self.p[1].generate(sampler)
         }
     }
 }
@@ -1,4 +1,5 @@
-use crate::utility::{random_double, random_int_range};
+use crate::sampler::Sampler;
+use crate::utility::random_int_range;
 use crate::vec3::Vec3;
 
 const POINT_COUNT: usize = 256;
@@ -10,32 +11,32 @@ pub struct Perlin {
     randvec: [Vec3; POINT_COUNT],
 }
 impl Perlin {
-    fn permute(p: &mut [i32; POINT_COUNT]) {
+    fn permute(p: &mut [i32; POINT_COUNT], sampler: &mut dyn Sampler) {
         for i in (0..POINT_COUNT).rev() {
-            let target = random_int_range(0, i as i32);
+            let target = random_int_range(0, i as i32, sampler);
             p.swap(i, target as usize);
         }
     }
-    fn perlin_generate_perm(p: &mut [i32; POINT_COUNT]) {
+    fn perlin_generate_perm(p: &mut [i32; POINT_COUNT], sampler: &mut dyn Sampler) {
         for (i, item) in p.iter_mut().enumerate().take(POINT_COUNT) {
             *item = i as i32;
         }
-        Self::permute(p);
+        Self::permute(p, sampler);
     }
-    pub fn new() -> Self {
+    pub fn new(sampler: &mut dyn Sampler) -> Self {
         let mut randfloat = [0.0; POINT_COUNT];
         for item in randfloat.iter_mut().take(POINT_COUNT) {
-            *item = random_double();
+            *item = sampler.get_1d();
         }
         let mut perm_x = [0i32; POINT_COUNT];
         let mut perm_y = [0i32; POINT_COUNT];
         let mut perm_z = [0i32; POINT_COUNT];
-        Self::perlin_generate_perm(&mut perm_x);
-        Self::perlin_generate_perm(&mut perm_y);
-        Self::perlin_generate_perm(&mut perm_z);
+        Self::perlin_generate_perm(&mut perm_x, sampler);
+        Self::perlin_generate_perm(&mut perm_y, sampler);
+        Self::perlin_generate_perm(&mut perm_z, sampler);
         let mut randvec = [Vec3::default(); POINT_COUNT];
         for item in randvec.iter_mut().take(POINT_COUNT) {
-            *item = Vec3::random_range(-1.0, 1.0).unit();
+            *item = Vec3::random_range(-1.0, 1.0, sampler).unit();
         }
         Self {
             perm_x,
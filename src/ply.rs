@@ -0,0 +1,453 @@
+use crate::asset::{LoadError, SearchPaths};
+use crate::color::{Color, srgb_to_linear};
+use crate::hittable_list::HittableList;
+use crate::material::Material;
+use crate::triangle::TriangleMesh;
+use crate::vec3::Vec3;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Clone, Copy)]
+enum PlyType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => PlyType::Int8,
+            "uchar" | "uint8" => PlyType::UInt8,
+            "short" | "int16" => PlyType::Int16,
+            "ushort" | "uint16" => PlyType::UInt16,
+            "int" | "int32" => PlyType::Int32,
+            "uint" | "uint32" => PlyType::UInt32,
+            "float" | "float32" => PlyType::Float32,
+            "double" | "float64" => PlyType::Float64,
+            _ => return None,
+        })
+    }
+    fn size(&self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::UInt8 => 1,
+            PlyType::Int16 | PlyType::UInt16 => 2,
+            PlyType::Int32 | PlyType::UInt32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+    // 整数类型的满量程值, 浮点类型返回 None
+    fn full_scale(&self) -> Option<f64> {
+        match self {
+            PlyType::Int8 => Some(i8::MAX as f64),
+            PlyType::UInt8 => Some(u8::MAX as f64),
+            PlyType::Int16 => Some(i16::MAX as f64),
+            PlyType::UInt16 => Some(u16::MAX as f64),
+            PlyType::Int32 => Some(i32::MAX as f64),
+            PlyType::UInt32 => Some(u32::MAX as f64),
+            PlyType::Float32 | PlyType::Float64 => None,
+        }
+    }
+}
+
+enum PlyProperty {
+    Scalar(String, PlyType),
+    List(String, PlyType, PlyType),
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+#[derive(PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+struct PlyReader<'a> {
+    path: PathBuf,
+    data: &'a [u8],
+    pos: usize,
+    format: PlyFormat,
+}
+impl PlyReader<'_> {
+    fn error(&self, message: &str) -> LoadError {
+        LoadError::Parse {
+            path: self.path.clone(),
+            message: message.to_string(),
+        }
+    }
+    fn read(&mut self, ty: PlyType) -> Result<f64, LoadError> {
+        if self.format == PlyFormat::Ascii {
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|c| c.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|c| !c.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            return std::str::from_utf8(&self.data[start..self.pos])
+                .ok()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| self.error("invalid value"));
+        }
+        let mut bytes = [0u8; 8];
+        let size = ty.size();
+        bytes[..size].copy_from_slice(
+            self.data
+                .get(self.pos..self.pos + size)
+                .ok_or_else(|| self.error("unexpected end of data"))?,
+        );
+        self.pos += size;
+        if self.format == PlyFormat::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+        Ok(match ty {
+            PlyType::Int8 => bytes[0] as i8 as f64,
+            PlyType::UInt8 => bytes[0] as f64,
+            PlyType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::UInt32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float64 => f64::from_le_bytes(bytes),
+        })
+    }
+}
+
+pub fn load_ply(
+    ply_filename: &str,
+    scale: f64,
+    mat: Arc<dyn Material>,
+) -> Result<HittableList, LoadError> {
+    let ply_path = SearchPaths::default()
+        .resolve(ply_filename)
+        .ok_or_else(|| LoadError::MissingFile(ply_filename.to_string()))?;
+    let data = std::fs::read(&ply_path).map_err(|err| LoadError::Parse {
+        path: ply_path.clone(),
+        message: err.to_string(),
+    })?;
+    let mut triangle_mesh = parse_ply(ply_path, &data, scale)?;
+    triangle_mesh.materials.push(mat);
+    println!("done model loading!");
+    let mut world = HittableList::new();
+    world.add(triangle_mesh.build_bvh());
+    Ok(world)
+}
+
+fn parse_ply(ply_path: PathBuf, data: &[u8], scale: f64) -> Result<TriangleMesh, LoadError> {
+    let (format, elements, body) = parse_header(data).map_err(|message| LoadError::Parse {
+        path: ply_path.clone(),
+        message: message.to_string(),
+    })?;
+    let mut reader = PlyReader {
+        path: ply_path,
+        data: &data[body..],
+        pos: 0,
+        format,
+    };
+    let mut triangle_mesh = TriangleMesh::new();
+    for element in &elements {
+        println!("Loading {} {}...", element.count, element.name);
+        for _ in 0..element.count {
+            let mut position = Vec3::default();
+            let mut normal = Vec3::default();
+            let mut texcoord = [0.0, 0.0];
+            let mut color = Color::new(1.0, 1.0, 1.0);
+            let (mut has_normal, mut has_texcoord, mut has_color) = (false, false, false);
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar(name, ty) => {
+                        let value = reader.read(*ty)?;
+                        if element.name != "vertex" {
+                            continue;
+                        }
+                        // 顶点颜色按 sRGB 编码处理: 整数按类型满量程归一化, 浮点视为已在 [0, 1],
+                        // 两者都解码到线性空间
+                        let channel = srgb_to_linear(match ty.full_scale() {
+                            Some(full_scale) => value / full_scale,
+                            None => value,
+                        });
+                        match name.as_str() {
+                            "x" => position.x = value,
+                            "y" => position.y = value,
+                            "z" => position.z = value,
+                            "nx" => (normal.x, has_normal) = (value, true),
+                            "ny" => (normal.y, has_normal) = (value, true),
+                            "nz" => (normal.z, has_normal) = (value, true),
+                            "u" | "s" | "texture_u" | "texture_s" => {
+                                (texcoord[0], has_texcoord) = (value, true)
+                            }
+                            "v" | "t" | "texture_v" | "texture_t" => {
+                                (texcoord[1], has_texcoord) = (value, true)
+                            }
+                            "red" | "diffuse_red" => (color.x, has_color) = (channel, true),
+                            "green" | "diffuse_green" => (color.y, has_color) = (channel, true),
+                            "blue" | "diffuse_blue" => (color.z, has_color) = (channel, true),
+                            _ => (),
+                        }
+                    }
+                    PlyProperty::List(name, count_type, item_type) => {
+                        let count = reader.read(*count_type)? as usize;
+                        let items = (0..count)
+                            .map(|_| reader.read(*item_type).map(|index| index as u32))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index")
+                        {
+                            for k in 1..items.len().saturating_sub(1) {
+                                triangle_mesh
+                                    .indices
+                                    .push([items[0], items[k], items[k + 1]]);
+                                triangle_mesh.face_materials.push(0);
+                            }
+                        }
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                triangle_mesh.positions.push(position * scale);
+                if has_normal {
+                    triangle_mesh.normals.push(normal);
+                }
+                if has_texcoord {
+                    triangle_mesh.texcoords.push(texcoord);
+                }
+                if has_color {
+                    triangle_mesh.colors.push(color);
+                }
+            }
+        }
+    }
+    let vertex_count = triangle_mesh.positions.len() as u32;
+    let face_count = triangle_mesh.indices.len();
+    triangle_mesh
+        .indices
+        .retain(|face| face.iter().all(|&i| i < vertex_count));
+    if triangle_mesh.indices.len() < face_count {
+        eprintln!(
+            "Warning: skipped {} faces of {:?} with out-of-range vertex indices.",
+            face_count - triangle_mesh.indices.len(),
+            reader.path
+        );
+        triangle_mesh
+            .face_materials
+            .truncate(triangle_mesh.indices.len());
+    }
+    Ok(triangle_mesh)
+}
+
+fn parse_header(data: &[u8]) -> Result<(PlyFormat, Vec<PlyElement>, usize), &'static str> {
+    let mut format = PlyFormat::Ascii;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    if !data.starts_with(b"ply") {
+        return Err("missing ply magic number");
+    }
+    let mut pos = 0;
+    loop {
+        let end = data[pos..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map(|offset| pos + offset)
+            .ok_or("header is not terminated")?;
+        let line = String::from_utf8_lossy(&data[pos..end]);
+        pos = end + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["end_header"] => break,
+            ["format", "ascii", ..] => format = PlyFormat::Ascii,
+            ["format", "binary_little_endian", ..] => format = PlyFormat::BinaryLittleEndian,
+            ["format", "binary_big_endian", ..] => format = PlyFormat::BinaryBigEndian,
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| "invalid element count")?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, name] => elements
+                .last_mut()
+                .ok_or("property outside of an element")?
+                .properties
+                .push(PlyProperty::List(
+                    name.to_string(),
+                    PlyType::parse(count_type).ok_or("unknown property type")?,
+                    PlyType::parse(item_type).ok_or("unknown property type")?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or("property outside of an element")?
+                .properties
+                .push(PlyProperty::Scalar(
+                    name.to_string(),
+                    PlyType::parse(ty).ok_or("unknown property type")?,
+                )),
+            _ => (),
+        }
+    }
+    Ok((format, elements, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+comment unit quad
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 2
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+3 0 2 7
+";
+
+    fn parse(data: &[u8]) -> Result<TriangleMesh, LoadError> {
+        parse_ply(PathBuf::from("test.ply"), data, 2.0)
+    }
+
+    fn binary_quad(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             property ushort red\nproperty ushort green\nproperty ushort blue\n\
+             element face 1\nproperty list uchar uint vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+        let vertices = [
+            ([0.0f32, 0.0, 0.0], [65535u16, 0, 0]),
+            ([1.0, 0.0, 0.0], [0, 65535, 0]),
+            ([1.0, 1.0, 0.0], [0, 0, 65535]),
+            ([0.0, 1.0, 0.0], [65535, 65535, 65535]),
+        ];
+        for (position, color) in vertices {
+            for x in position {
+                data.extend(if big_endian {
+                    x.to_be_bytes()
+                } else {
+                    x.to_le_bytes()
+                });
+            }
+            for c in color {
+                data.extend(if big_endian {
+                    c.to_be_bytes()
+                } else {
+                    c.to_le_bytes()
+                });
+            }
+        }
+        data.push(4);
+        for index in [0u32, 1, 2, 3] {
+            data.extend(if big_endian {
+                index.to_be_bytes()
+            } else {
+                index.to_le_bytes()
+            });
+        }
+        data
+    }
+
+    fn assert_quad(mesh: &TriangleMesh) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.positions[2].x, 2.0);
+        assert_eq!(mesh.positions[2].y, 2.0);
+        assert_eq!(mesh.indices[..2], [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.colors.len(), 4);
+        assert_eq!(mesh.colors[0].x, 1.0);
+        assert_eq!(mesh.colors[1].x, 0.0);
+        assert_eq!(mesh.colors[3].z, 1.0);
+    }
+
+    #[test]
+    fn ascii_faces_are_fanned_and_bad_indices_skipped() {
+        let mesh = parse(ASCII_QUAD.as_bytes()).unwrap();
+        assert_quad(&mesh);
+        // 第二个面引用了不存在的顶点 7
+        assert_eq!(mesh.indices.len(), 2);
+        assert_eq!(mesh.face_materials.len(), 2);
+    }
+
+    #[test]
+    fn binary_formats_match_ascii() {
+        for big_endian in [false, true] {
+            let mesh = parse(&binary_quad(big_endian)).unwrap();
+            assert_quad(&mesh);
+        }
+    }
+
+    #[test]
+    fn integer_colours_are_normalised_by_type_range() {
+        let data = ASCII_QUAD.replace("255 255 255", "128 128 128");
+        let mesh = parse(data.as_bytes()).unwrap();
+        assert!((mesh.colors[3].x - srgb_to_linear(128.0 / 255.0)).abs() < 1e-12);
+        let mesh = parse(&binary_quad(false)).unwrap();
+        assert_eq!(mesh.colors[3].y, 1.0);
+    }
+
+    #[test]
+    fn float_colours_are_decoded_like_integer_colours() {
+        let data = ASCII_QUAD
+            .replace("uchar red", "float red")
+            .replace("uchar green", "float green")
+            .replace("uchar blue", "double blue")
+            .replace("255 255 255", "0.5 0.5 0.5")
+            .replace("255", "1");
+        let mesh = parse(data.as_bytes()).unwrap();
+        assert_eq!(mesh.colors[0].x, 1.0);
+        for c in [mesh.colors[3].x, mesh.colors[3].y, mesh.colors[3].z] {
+            assert!((c - srgb_to_linear(0.5)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_parse_errors() {
+        let truncated = binary_quad(false);
+        for data in [
+            b"obj\n".to_vec(),
+            b"ply\nformat ascii 1.0\nelement vertex 1\n".to_vec(),
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n".to_vec(),
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\nabc\n"
+                .to_vec(),
+            truncated[..truncated.len() - 3].to_vec(),
+        ] {
+            assert!(matches!(parse(&data), Err(LoadError::Parse { .. })));
+        }
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        let mat = Arc::new(crate::material::Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let err = load_ply("does/not/exist.ply", 1.0, mat).err().unwrap();
+        assert!(matches!(err, LoadError::MissingFile(ref name) if name == "does/not/exist.ply"));
+    }
+}
@@ -4,7 +4,8 @@ use crate::hittable_list::HittableList;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::utility::{INFINITY, random_double};
+use crate::sampler::Sampler;
+use crate::utility::INFINITY;
 use crate::vec3::Vec3;
 use std::sync::Arc;
 
@@ -37,60 +38,74 @@ impl Quad {
             area: n.length(),
         }
     }
-    fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
+    fn is_interior(a: f64, b: f64) -> bool {
         let unit_interval = Interval::new(0.0, 1.0);
-        if !unit_interval.contains(a) || !unit_interval.contains(b) {
-            return false;
-        }
-        rec.u = a;
-        rec.v = b;
-        true
+        unit_interval.contains(a) && unit_interval.contains(b)
     }
-}
-impl Hittable for Quad {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64, f64)> {
         let denom = self.normal.dot(&r.direction);
         if denom.abs() < 1e-8 {
-            return false;
+            return None;
         }
         let t = (self.d - self.normal.dot(&r.origin)) / denom;
         if !ray_t.contains(t) {
-            return false;
+            return None;
         }
-        let intersection = r.at(t);
-        let planar_hitpt_vector = intersection - self.q;
+        let planar_hitpt_vector = r.at(t) - self.q;
         let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
         let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
-        if !Self::is_interior(alpha, beta, rec) {
-            return false;
+        if !Self::is_interior(alpha, beta) {
+            return None;
         }
+        Some((t, alpha, beta))
+    }
+}
+impl Hittable for Quad {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, alpha, beta)) = self.intersect(r, ray_t) else {
+            return false;
+        };
         rec.t = t;
-        rec.p = intersection;
-        rec.mat = self.mat.clone();
-        rec.set_face_normal(r, self.normal, alpha, beta);
-        if random_double() > self.mat.get_alpha(alpha, beta) {
+        rec.p = r.at(t);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.set_material(&self.mat);
+        rec.set_shading_frame(r, self.normal, self.normal, self.u, self.v);
+        if !rec.mat.alpha_test(alpha, beta, sampler) {
             return false;
         }
         true
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t)
+            .is_some_and(|(_, alpha, beta)| self.mat.alpha_test(alpha, beta, sampler))
+    }
     fn bounding_box(&self) -> Aabb {
         self.bbox
     }
-    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
         let mut rec = HitRecord::default();
         if !self.hit(
             &Ray::new(*origin, *direction),
             &Interval::new(0.001, INFINITY),
             &mut rec,
+            sampler,
         ) {
             return 0.0;
         }
         let distance_squared = rec.t * rec.t * direction.squared_length();
-        let cosine = (direction.dot(&rec.normal) / direction.length()).abs();
+        let cosine = (direction.dot(&rec.geometric_normal) / direction.length()).abs();
         distance_squared / (cosine * self.area)
     }
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        let p = self.q + (self.u * random_double()) + (self.v * random_double());
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let p = self.q + (self.u * r1) + (self.v * r2);
         p - *origin
     }
 }
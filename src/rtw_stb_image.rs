@@ -1,3 +1,4 @@
+use crate::asset::{LoadError, SearchPaths};
 use stb_image::image;
 
 pub const BYTES_PER_PIXEL: usize = 3;
@@ -12,40 +13,26 @@ pub struct RtwImage {
 }
 impl RtwImage {
     pub fn new(image_filename: &str) -> Self {
-        let filename = image_filename;
-        let imagedir = std::env::var("RTW_IMAGES").unwrap_or_else(|_| String::from("assets"));
+        Self::open(image_filename, &SearchPaths::default()).unwrap_or_else(|err| {
+            eprintln!("Warning: {}, using a placeholder.", err);
+            Self::default()
+        })
+    }
+    pub fn open(image_filename: &str, search_paths: &SearchPaths) -> Result<Self, LoadError> {
         let mut _self = Self::default();
-        if !imagedir.is_empty() && _self.load(&format!("{}/{}", imagedir, filename)) {
-            return _self;
-        }
-        if _self.load(filename) {
-            return _self;
-        }
-        if _self.load(&format!("assets/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("images/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("../images/{}", filename)) {
-            return _self;
+        match search_paths.resolve(image_filename) {
+            Some(path) if _self.load(&path.to_string_lossy()) => Ok(_self),
+            _ => Err(LoadError::MissingTexture(image_filename.to_string())),
         }
-        if _self.load(&format!("../../images/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("../../../images/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("../../../../images/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("../../../../../images/{}", filename)) {
-            return _self;
-        }
-        if _self.load(&format!("../../../../../../images/{}", filename)) {
-            return _self;
+    }
+    pub fn from_rgb(width: usize, height: usize, data: Vec<u8>) -> Self {
+        assert_eq!(data.len(), width * height * BYTES_PER_PIXEL);
+        Self {
+            data,
+            image_width: width,
+            image_height: height,
+            bytes_per_scanline: width * BYTES_PER_PIXEL,
         }
-        panic!("ERROR: Could not load image file \"{}\".", filename);
     }
     pub fn load(&mut self, filename: &str) -> bool {
         let load_result = image::load_with_depth(filename, BYTES_PER_PIXEL, false);
@@ -0,0 +1,581 @@
+use std::fmt;
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+const SOBOL_MATRIX_SIZE: usize = 32;
+const PRIME_COUNT: usize = 1000;
+const RADICAL_INVERSE_PRECISION: f64 = 1.0 / (1u64 << 32) as f64;
+
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SamplerType {
+    Independent,
+    Stratified,
+    Halton,
+    #[default]
+    Sobol,
+    BlueNoise,
+}
+impl SamplerType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "independent" => Some(SamplerType::Independent),
+            "stratified" => Some(SamplerType::Stratified),
+            "halton" => Some(SamplerType::Halton),
+            "sobol" => Some(SamplerType::Sobol),
+            "bluenoise" | "blue-noise" | "zsobol" => Some(SamplerType::BlueNoise),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for SamplerType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SamplerType::Independent => "independent",
+            SamplerType::Stratified => "stratified",
+            SamplerType::Halton => "halton",
+            SamplerType::Sobol => "sobol",
+            SamplerType::BlueNoise => "blue-noise",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, &v| mix_bits(h ^ mix_bits(v)))
+}
+
+// Kensler 的可逆哈希置换, 返回 [0, l) 的排列中第 i 个元素
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+lazy_static! {
+    static ref PRIMES: Vec<u64> = {
+        let mut primes: Vec<u64> = Vec::with_capacity(PRIME_COUNT);
+        let mut n = 2;
+        while primes.len() < PRIME_COUNT {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= n)
+                .all(|&p| n % p != 0)
+            {
+                primes.push(n);
+            }
+            n += 1;
+        }
+        primes
+    };
+}
+
+fn owen_scrambled_radical_inverse(base: u64, mut a: u64, hash: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut prefix = 0u64;
+    let mut value = 0.0;
+    // 即使 a 的有效位已取完, 仍继续置换后续的 0 位, 直到低于 32 位精度
+    while (base - 1) as f64 * inv_base_m >= RADICAL_INVERSE_PRECISION {
+        let next = a / base;
+        let digit = a - next * base;
+        let digit_hash = mix_bits(hash ^ prefix) as u32;
+        let digit = permutation_element(digit as u32, base as u32, digit_hash) as u64;
+        prefix = prefix.wrapping_mul(base).wrapping_add(digit);
+        inv_base_m *= inv_base;
+        value += digit as f64 * inv_base_m;
+        a = next;
+    }
+    value.min(ONE_MINUS_EPSILON)
+}
+
+// 前两维 Sobol 生成矩阵: 第 0 维为 van der Corput, 第 1 维由本原多项式 x + 1 生成
+const SOBOL_MATRICES: [[u32; SOBOL_MATRIX_SIZE]; 2] = {
+    let mut matrices = [[0u32; SOBOL_MATRIX_SIZE]; 2];
+    let mut v = 1u32 << 31;
+    let mut i = 0;
+    while i < SOBOL_MATRIX_SIZE {
+        matrices[0][i] = 1u32 << (31 - i);
+        matrices[1][i] = v;
+        v ^= v >> 1;
+        i += 1;
+    }
+    matrices
+};
+
+fn fast_owen_scramble(mut v: u32, seed: u32) -> u32 {
+    v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20_adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x0552_6c56);
+    v ^= v.wrapping_mul(0x53a2_2864);
+    v.reverse_bits()
+}
+
+fn sobol_sample(mut index: u64, dimension: usize, seed: u32) -> f64 {
+    let mut v = 0u32;
+    let mut i = 0;
+    while index != 0 && i < SOBOL_MATRIX_SIZE {
+        if index & 1 != 0 {
+            v ^= SOBOL_MATRICES[dimension][i];
+        }
+        index >>= 1;
+        i += 1;
+    }
+    let v = fast_owen_scramble(v, seed);
+    (v as f64 * (1.0 / (1u64 << 32) as f64)).min(ONE_MINUS_EPSILON)
+}
+
+fn encode_morton2(x: u32, y: u32) -> u64 {
+    let spread = |v: u32| {
+        let mut v = v as u64;
+        v = (v ^ (v << 16)) & 0x0000_ffff_0000_ffff;
+        v = (v ^ (v << 8)) & 0x00ff_00ff_00ff_00ff;
+        v = (v ^ (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        v = (v ^ (v << 2)) & 0x3333_3333_3333_3333;
+        (v ^ (v << 1)) & 0x5555_5555_5555_5555
+    };
+    (spread(y) << 1) | spread(x)
+}
+
+fn pixel_key(i: u32, j: u32) -> u64 {
+    ((j as u64) << 32) | i as u64
+}
+
+// SplitMix64, 每个像素样本由 (种子, 像素, 样本序号) 重新播种, 与线程调度无关
+pub struct IndependentSampler {
+    seed: u64,
+    state: u64,
+}
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: mix_bits(seed),
+        }
+    }
+}
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.state =
+            mix_bits(mix_bits(self.seed ^ mix_bits(pixel_key(i, j))) ^ sample_index as u64);
+    }
+    fn get_1d(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+// 一维: 每一维独立打乱分层顺序, 层内抖动
+// 二维: Kensler 的相关多重抖动 (CMJ), 样本数不必是平方数
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    x_samples: u32,
+    y_samples: u32,
+    seed: u64,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+    rng: IndependentSampler,
+}
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        let x_samples = (samples_per_pixel as f64).sqrt().ceil() as u32;
+        Self {
+            samples_per_pixel,
+            x_samples,
+            y_samples: samples_per_pixel.div_ceil(x_samples),
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+            rng: IndependentSampler::new(seed),
+        }
+    }
+    fn dimension_hash(&mut self, dimensions: u64) -> u32 {
+        let h = hash(&[self.pixel, self.dimension, self.seed]);
+        self.dimension += dimensions;
+        h as u32
+    }
+}
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng.start_pixel_sample(i, j, sample_index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let p = self.dimension_hash(1);
+        let stratum = permutation_element(self.sample_index, self.samples_per_pixel, p) as f64;
+        ((stratum + self.rng.get_1d()) / self.samples_per_pixel as f64).min(ONE_MINUS_EPSILON)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let p = self.dimension_hash(2);
+        let (m, n) = (self.x_samples, self.y_samples);
+        // 非方形形式: y 直接按 N 分层, x 落在 m * n 个细分层中互不相同的一个,
+        // 样本数不足 m * n 时也不会留下空行
+        let s = permutation_element(
+            self.sample_index,
+            self.samples_per_pixel,
+            p.wrapping_mul(0x5163_3e2d),
+        );
+        let sx = permutation_element(s % m, m, p.wrapping_mul(0xa511_e9b3)) as f64;
+        let sy = permutation_element(s / m, n, p.wrapping_mul(0x63d8_3595)) as f64;
+        let jx = self.rng.get_1d();
+        let jy = self.rng.get_1d();
+        (
+            ((sx + (sy + jx) / n as f64) / m as f64).min(ONE_MINUS_EPSILON),
+            ((s as f64 + jy) / self.samples_per_pixel as f64).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+// 每个像素使用独立 Owen 扰乱的 Halton 序列, 第 d 维以第 d 个素数为底
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+}
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+}
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let base = PRIMES[self.dimension as usize % PRIME_COUNT];
+        let h = hash(&[self.pixel, self.dimension, self.seed]);
+        self.dimension += 1;
+        owen_scrambled_radical_inverse(base, self.sample_index as u64, h)
+    }
+}
+
+// 填充式 Sobol: 每一维(或一对维度)打乱样本序号后取前两维 Sobol 点, 并做 Owen 扰乱
+pub struct SobolSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+}
+impl SobolSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        Self {
+            samples_per_pixel,
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+    fn shuffled_index(&mut self, dimensions: u64) -> (u64, u64) {
+        let h = hash(&[self.pixel, self.dimension, self.seed]);
+        self.dimension += dimensions;
+        let index = permutation_element(self.sample_index, self.samples_per_pixel, h as u32);
+        (index as u64, mix_bits(h))
+    }
+}
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.pixel = pixel_key(i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let (index, scramble) = self.shuffled_index(1);
+        sobol_sample(index, 0, scramble as u32)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, scramble) = self.shuffled_index(2);
+        (
+            sobol_sample(index, 0, scramble as u32),
+            sobol_sample(index, 1, (scramble >> 32) as u32),
+        )
+    }
+}
+
+const BASE4_PERMUTATIONS: [[u8; 4]; 24] = [
+    [0, 1, 2, 3],
+    [0, 1, 3, 2],
+    [0, 2, 1, 3],
+    [0, 2, 3, 1],
+    [0, 3, 2, 1],
+    [0, 3, 1, 2],
+    [1, 0, 2, 3],
+    [1, 0, 3, 2],
+    [1, 2, 0, 3],
+    [1, 2, 3, 0],
+    [1, 3, 2, 0],
+    [1, 3, 0, 2],
+    [2, 1, 0, 3],
+    [2, 1, 3, 0],
+    [2, 0, 1, 3],
+    [2, 0, 3, 1],
+    [2, 3, 0, 1],
+    [2, 3, 1, 0],
+    [3, 1, 2, 0],
+    [3, 1, 0, 2],
+    [3, 2, 1, 0],
+    [3, 2, 0, 1],
+    [3, 0, 2, 1],
+    [3, 0, 1, 2],
+];
+
+// Ahmed & Wonka 的 ZSampler: 按 Morton 序把全图像素串成一条 Sobol 序列,
+// 再逐个 4 进制位随机置换, 使相邻像素的误差呈蓝噪声分布
+pub struct ZSobolSampler {
+    log2_spp: u32,
+    base4_digits: u32,
+    seed: u64,
+    morton_index: u64,
+    dimension: u64,
+}
+impl ZSobolSampler {
+    pub fn new(samples_per_pixel: u32, image_width: u32, image_height: u32, seed: u64) -> Self {
+        let log2_spp = samples_per_pixel.next_power_of_two().trailing_zeros();
+        let log2_res = image_width
+            .max(image_height)
+            .next_power_of_two()
+            .trailing_zeros();
+        Self {
+            log2_spp,
+            base4_digits: log2_res + log2_spp.div_ceil(2),
+            seed,
+            morton_index: 0,
+            dimension: 0,
+        }
+    }
+    fn sample_index(&self) -> u64 {
+        let pow2_samples = self.log2_spp & 1 == 1;
+        let last_digit = pow2_samples as u32;
+        let dimension_bits = 0x5555_5555u64.wrapping_mul(self.dimension);
+        let mut sample_index = 0u64;
+        for i in (last_digit..self.base4_digits).rev() {
+            let digit_shift = 2 * i - last_digit;
+            let digit = (self.morton_index >> digit_shift) & 3;
+            let higher_digits = self.morton_index >> (digit_shift + 2);
+            let p = (mix_bits(higher_digits ^ dimension_bits) >> 24) % 24;
+            let digit = BASE4_PERMUTATIONS[p as usize][digit as usize] as u64;
+            sample_index |= digit << digit_shift;
+        }
+        if pow2_samples {
+            let digit = self.morton_index & 1;
+            sample_index |= digit ^ (mix_bits((self.morton_index >> 1) ^ dimension_bits) & 1);
+        }
+        sample_index
+    }
+}
+impl Sampler for ZSobolSampler {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, sample_index: u32) {
+        self.morton_index = (encode_morton2(i, j) << self.log2_spp) | sample_index as u64;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let index = self.sample_index();
+        let scramble = hash(&[self.dimension, self.seed]);
+        self.dimension += 1;
+        sobol_sample(index, 0, scramble as u32)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let index = self.sample_index();
+        let scramble = hash(&[self.dimension, self.seed]);
+        self.dimension += 2;
+        (
+            sobol_sample(index, 0, scramble as u32),
+            sobol_sample(index, 1, (scramble >> 32) as u32),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel_points_2d(sampler: &mut dyn Sampler, spp: u32, i: u32, j: u32) -> Vec<(f64, f64)> {
+        (0..spp)
+            .map(|index| {
+                sampler.start_pixel_sample(i, j, index);
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stratified_2d_covers_every_stratum() {
+        for spp in [5, 10, 5000] {
+            let mut sampler = StratifiedSampler::new(spp, 11);
+            let (m, n) = (sampler.x_samples, sampler.y_samples);
+            for (i, j) in [(0, 0), (3, 7), (120, 45)] {
+                let points = pixel_points_2d(&mut sampler, spp, i, j);
+                // y: N 个分层各有一个样本
+                let mut rows: Vec<u32> = points.iter().map(|p| (p.1 * spp as f64) as u32).collect();
+                rows.sort_unstable();
+                assert!(rows.iter().copied().eq(0..spp), "spp {spp}: y strata");
+                // x: m * n 个细分层互不重复, m 列中样本数相差不超过 1
+                let mut fine: Vec<u32> = points
+                    .iter()
+                    .map(|p| (p.0 * (m * n) as f64) as u32)
+                    .collect();
+                fine.sort_unstable();
+                fine.dedup();
+                assert_eq!(fine.len(), spp as usize, "spp {spp}: x sub-strata");
+                let mut columns = vec![0u32; m as usize];
+                for p in &points {
+                    columns[(p.0 * m as f64) as usize] += 1;
+                }
+                assert!(
+                    columns
+                        .iter()
+                        .all(|&c| c == spp / m || c == spp.div_ceil(m)),
+                    "spp {spp}: columns {columns:?}"
+                );
+            }
+        }
+    }
+
+    fn all_samplers(spp: u32, seed: u64) -> Vec<(&'static str, Box<dyn Sampler>)> {
+        vec![
+            ("independent", Box::new(IndependentSampler::new(seed))),
+            ("stratified", Box::new(StratifiedSampler::new(spp, seed))),
+            ("halton", Box::new(HaltonSampler::new(seed))),
+            ("sobol", Box::new(SobolSampler::new(spp, seed))),
+            ("zsobol", Box::new(ZSobolSampler::new(spp, 64, 64, seed))),
+        ]
+    }
+
+    fn sample_values(sampler: &mut dyn Sampler, i: u32, j: u32, index: u32) -> Vec<u64> {
+        sampler.start_pixel_sample(i, j, index);
+        let mut values = vec![sampler.get_1d().to_bits()];
+        let (x, y) = sampler.get_2d();
+        values.extend([x.to_bits(), y.to_bits()]);
+        values.extend((0..5).map(|_| sampler.get_1d().to_bits()));
+        values
+    }
+
+    #[test]
+    fn samples_depend_only_on_seed_pixel_and_index() {
+        let pixels = [(0, 0, 0), (5, 9, 3), (63, 1, 15), (5, 9, 4)];
+        for ((name, mut a), (_, mut b)) in
+            all_samplers(16, 42).into_iter().zip(all_samplers(16, 42))
+        {
+            let forward: Vec<_> = pixels
+                .iter()
+                .map(|&(i, j, index)| sample_values(a.as_mut(), i, j, index))
+                .collect();
+            // 以相反顺序访问, 模拟不同的线程调度
+            let mut backward: Vec<_> = pixels
+                .iter()
+                .rev()
+                .map(|&(i, j, index)| sample_values(b.as_mut(), i, j, index))
+                .collect();
+            backward.reverse();
+            assert_eq!(forward, backward, "{name}");
+            assert_ne!(forward[1], forward[3], "{name}: sample index ignored");
+        }
+        for ((name, mut a), (_, mut b)) in all_samplers(16, 1).into_iter().zip(all_samplers(16, 2))
+        {
+            assert_ne!(
+                sample_values(a.as_mut(), 3, 4, 0),
+                sample_values(b.as_mut(), 3, 4, 0),
+                "{name}: seed ignored"
+            );
+        }
+    }
+
+    // 每个 cells_x x cells_y 网格单元恰好一个样本
+    fn assert_one_per_cell(points: &[(f64, f64)], cells_x: u32, cells_y: u32, name: &str) {
+        let mut cells: Vec<u32> = points
+            .iter()
+            .map(|p| (p.1 * cells_y as f64) as u32 * cells_x + (p.0 * cells_x as f64) as u32)
+            .collect();
+        cells.sort_unstable();
+        assert!(
+            cells.iter().copied().eq(0..cells_x * cells_y),
+            "{name}: {cells_x}x{cells_y}"
+        );
+    }
+
+    #[test]
+    fn low_discrepancy_samplers_form_nets() {
+        let mut sobol = SobolSampler::new(16, 3);
+        let mut zsobol = ZSobolSampler::new(16, 64, 64, 3);
+        for (name, sampler) in [
+            ("sobol", &mut sobol as &mut dyn Sampler),
+            ("zsobol", &mut zsobol),
+        ] {
+            for (i, j) in [(0, 0), (17, 40)] {
+                let points = pixel_points_2d(sampler, 16, i, j);
+                for (cells_x, cells_y) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                    assert_one_per_cell(&points, cells_x, cells_y, name);
+                }
+            }
+        }
+        // Halton 前两维以 2 和 3 为底, 6 个样本构成 2 x 3 网格
+        let mut halton = HaltonSampler::new(3);
+        for (i, j) in [(0, 0), (17, 40)] {
+            let points = pixel_points_2d(&mut halton, 6, i, j);
+            assert_one_per_cell(&points, 2, 3, "halton");
+        }
+    }
+}
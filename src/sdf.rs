@@ -0,0 +1,430 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::sphere::Sphere;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+const MAX_STEPS: u32 = 512;
+
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: &Vec3) -> f64;
+}
+
+fn abs(v: &Vec3) -> Vec3 {
+    Vec3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+fn max_zero(v: &Vec3) -> Vec3 {
+    Vec3::new(v.x.max(0.0), v.y.max(0.0), v.z.max(0.0))
+}
+fn mix(a: f64, b: f64, h: f64) -> f64 {
+    a * (1.0 - h) + b * h
+}
+
+pub struct SdfSphere {
+    center: Vec3,
+    radius: f64,
+}
+impl SdfSphere {
+    pub fn new(center: Vec3, radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+impl Sdf for SdfSphere {
+    fn distance(&self, p: &Vec3) -> f64 {
+        (*p - self.center).length() - self.radius
+    }
+}
+
+pub struct SdfEllipsoid {
+    center: Vec3,
+    radii: Vec3,
+}
+impl SdfEllipsoid {
+    pub fn new(center: Vec3, radii: Vec3) -> Self {
+        Self { center, radii }
+    }
+}
+impl Sdf for SdfEllipsoid {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let q = *p - self.center;
+        let k0 = Vec3::new(q.x / self.radii.x, q.y / self.radii.y, q.z / self.radii.z).length();
+        // 内部 k0 (k0 - 1) / k1 会高估距离 (扁椭球可达数倍), 步进会越过表面.
+        // 缩放到单位球的映射的 Lipschitz 常数为 1 / 最短半轴, 故 (k0 - 1) * 最短半轴是保守下界
+        if k0 < 1.0 {
+            return (k0 - 1.0) * self.radii.x.min(self.radii.y).min(self.radii.z);
+        }
+        // 外部该近似不超过真实距离, 且在表面附近精确到一阶
+        let k1 = Vec3::new(
+            q.x / (self.radii.x * self.radii.x),
+            q.y / (self.radii.y * self.radii.y),
+            q.z / (self.radii.z * self.radii.z),
+        )
+        .length();
+        k0 * (k0 - 1.0) / k1
+    }
+}
+
+pub struct SdfBox {
+    center: Vec3,
+    half_extents: Vec3,
+    radius: f64,
+}
+impl SdfBox {
+    pub fn new(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            center,
+            half_extents,
+            radius: 0.0,
+        }
+    }
+    pub fn new_rounded(center: Vec3, half_extents: Vec3, radius: f64) -> Self {
+        Self {
+            center,
+            half_extents: half_extents - Vec3::new(radius, radius, radius),
+            radius,
+        }
+    }
+}
+impl Sdf for SdfBox {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let q = abs(&(*p - self.center)) - self.half_extents;
+        max_zero(&q).length() + q.x.max(q.y).max(q.z).min(0.0) - self.radius
+    }
+}
+
+pub struct SdfTorus {
+    center: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+}
+impl SdfTorus {
+    pub fn new(center: Vec3, major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+impl Sdf for SdfTorus {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let q = *p - self.center;
+        let ring = (q.x * q.x + q.z * q.z).sqrt() - self.major_radius;
+        (ring * ring + q.y * q.y).sqrt() - self.minor_radius
+    }
+}
+
+pub struct SdfCapsule {
+    a: Vec3,
+    b: Vec3,
+    radius: f64,
+}
+impl SdfCapsule {
+    pub fn new(a: Vec3, b: Vec3, radius: f64) -> Self {
+        Self { a, b, radius }
+    }
+}
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let pa = *p - self.a;
+        let ba = self.b - self.a;
+        let h = Interval::new(0.0, 1.0).clamp(pa.dot(&ba) / ba.dot(&ba));
+        (pa - ba * h).length() - self.radius
+    }
+}
+
+pub struct SmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+impl SmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        if self.k <= 0.0 {
+            return d1.min(d2);
+        }
+        let h = Interval::new(0.0, 1.0).clamp(0.5 + 0.5 * (d2 - d1) / self.k);
+        mix(d2, d1, h) - self.k * h * (1.0 - h)
+    }
+}
+
+pub struct SmoothSubtraction {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+impl SmoothSubtraction {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+impl Sdf for SmoothSubtraction {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        if self.k <= 0.0 {
+            return d1.max(-d2);
+        }
+        let h = Interval::new(0.0, 1.0).clamp(0.5 - 0.5 * (d1 + d2) / self.k);
+        mix(d1, -d2, h) + self.k * h * (1.0 - h)
+    }
+}
+
+pub struct SmoothIntersection {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+impl SmoothIntersection {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+impl Sdf for SmoothIntersection {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        if self.k <= 0.0 {
+            return d1.max(d2);
+        }
+        let h = Interval::new(0.0, 1.0).clamp(0.5 - 0.5 * (d2 - d1) / self.k);
+        mix(d2, d1, h) + self.k * h * (1.0 - h)
+    }
+}
+
+pub struct SdfObject {
+    sdf: Arc<dyn Sdf>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    epsilon: f64,
+}
+impl SdfObject {
+    pub fn new(sdf: Arc<dyn Sdf>, mat: Arc<dyn Material>, bbox: Aabb) -> Self {
+        let extent = bbox.x.size().max(bbox.y.size()).max(bbox.z.size());
+        Self {
+            sdf,
+            mat,
+            bbox,
+            epsilon: extent * 1e-6,
+        }
+    }
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.sdf.distance(&(*p + dx)) - self.sdf.distance(&(*p - dx)),
+            self.sdf.distance(&(*p + dy)) - self.sdf.distance(&(*p - dy)),
+            self.sdf.distance(&(*p + dz)) - self.sdf.distance(&(*p - dz)),
+        )
+        .unit()
+    }
+    fn march(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Vec3)> {
+        let span = self.bbox.hit_interval(r, ray_t)?;
+        let dir_length = r.direction.length();
+        let mut t = span.min;
+        let mut d = self.sdf.distance(&r.at(t));
+        let mut steps = 0;
+        // 只有起点在包围盒内 (如从表面出发的次级光线) 时才先离开起点所在的表面;
+        // 从外部进入时包围盒可能与平面重合, 入口处的零距离就是命中
+        while span.min <= ray_t.min && d.abs() < self.epsilon * 10.0 && steps < MAX_STEPS {
+            t += self.epsilon * 10.0 / dir_length;
+            d = self.sdf.distance(&r.at(t));
+            steps += 1;
+        }
+        let side = d.signum();
+        while steps < MAX_STEPS && t <= span.max {
+            let p = r.at(t);
+            d = side * self.sdf.distance(&p);
+            if d < self.epsilon {
+                return Some((t, p));
+            }
+            t += d / dir_length;
+            steps += 1;
+        }
+        None
+    }
+}
+impl Hittable for SdfObject {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, p)) = self.march(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = p;
+        let outward_normal = self.normal(&p);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&outward_normal);
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, outward_normal, rec.u, rec.v);
+        rec.mat.alpha_test(rec.u, rec.v, sampler)
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.march(r, ray_t).is_some_and(|(_, p)| {
+            let (u, v) = Sphere::get_sphere_uv(&self.normal(&p));
+            self.mat.alpha_test(u, v, sampler)
+        })
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::sampler::IndependentSampler;
+    use crate::utility::INFINITY;
+
+    fn object(sdf: Arc<dyn Sdf>, min: Vec3, max: Vec3) -> SdfObject {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        SdfObject::new(sdf, mat, Aabb::new_points(&min, &max))
+    }
+
+    fn hit_t(object: &SdfObject, r: &Ray) -> Option<f64> {
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        object
+            .hit(r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler)
+            .then_some(rec.t)
+    }
+
+    #[test]
+    fn sphere_tracer_matches_analytic_sphere() {
+        let center = Vec3::new(0.0, 0.0, -5.0);
+        let sphere = object(
+            Arc::new(SdfSphere::new(center, 1.0)),
+            Vec3::new(-1.0, -1.0, -6.0),
+            Vec3::new(1.0, 1.0, -4.0),
+        );
+        let mut sampler = IndependentSampler::new(3);
+        for _ in 0..100 {
+            let origin = Vec3::random_range(-3.0, 3.0, &mut sampler);
+            let target = center + Vec3::random_unit_vector(&mut sampler) * 0.9;
+            let r = Ray::new(origin, (target - origin) * 2.0);
+            // |o + t d - c|² = 1 的较小根
+            let oc = r.origin - center;
+            let a = r.direction.squared_length();
+            let h = r.direction.dot(&oc);
+            let c = oc.squared_length() - 1.0;
+            let expected = (-h - (h * h - a * c).sqrt()) / a;
+            let t = hit_t(&sphere, &r).unwrap();
+            assert!((t - expected).abs() * r.direction.length() < 1e-5);
+        }
+        // 从内部出发命中背面
+        let r = Ray::new(center, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit_t(&sphere, &r).unwrap() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sphere_tracer_matches_analytic_box() {
+        let half = Vec3::new(1.0, 2.0, 3.0);
+        let cube = object(
+            Arc::new(SdfBox::new(Vec3::new(0.0, 0.0, 0.0), half)),
+            -half,
+            half,
+        );
+        let r = Ray::new(Vec3::new(0.3, 0.4, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!((hit_t(&cube, &r).unwrap() - 7.0).abs() < 1e-5);
+        let r = Ray::new(Vec3::new(5.0, 0.5, 0.5), Vec3::new(-1.0, 0.1, 0.0));
+        assert!((hit_t(&cube, &r).unwrap() - 4.0).abs() < 1e-5);
+        // 擦过棱边外侧
+        let r = Ray::new(Vec3::new(1.01, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(hit_t(&cube, &r).is_none());
+    }
+
+    #[test]
+    fn rays_inside_flat_ellipsoids_do_not_overstep() {
+        let radii = Vec3::new(1.0, 1.0, 0.1);
+        let ellipsoid = object(
+            Arc::new(SdfEllipsoid::new(Vec3::new(0.0, 0.0, 0.0), radii)),
+            -radii,
+            radii,
+        );
+        let mut sampler = IndependentSampler::new(5);
+        for _ in 0..200 {
+            let origin = Vec3::new(
+                sampler.get_1d() - 0.5,
+                sampler.get_1d() - 0.5,
+                0.1 * (sampler.get_1d() - 0.5),
+            );
+            let r = Ray::new(origin, Vec3::random_unit_vector(&mut sampler));
+            // 缩放到单位球后求出射根
+            let o = Vec3::new(origin.x / radii.x, origin.y / radii.y, origin.z / radii.z);
+            let d = Vec3::new(
+                r.direction.x / radii.x,
+                r.direction.y / radii.y,
+                r.direction.z / radii.z,
+            );
+            let a = d.squared_length();
+            let h = d.dot(&o);
+            let c = o.squared_length() - 1.0;
+            let expected = (-h + (h * h - a * c).sqrt()) / a;
+            let t = hit_t(&ellipsoid, &r).unwrap();
+            assert!((t - expected).abs() < 1e-4, "t {} expected {}", t, expected);
+        }
+    }
+
+    #[test]
+    fn smooth_operations_blend_within_k() {
+        let a: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Vec3::new(-1.0, 0.0, 0.0), 0.5));
+        let b: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Vec3::new(1.0, 0.0, 0.0), 0.5));
+        let k = 0.4;
+        // 到两球等距的点: h = 1/2, 混合项为 k / 4
+        let p = Vec3::new(0.0, 0.3, 0.0);
+        let d = a.distance(&p);
+        let union = SmoothUnion::new(a.clone(), b.clone(), k);
+        assert!((union.distance(&p) - (d - k / 4.0)).abs() < 1e-12);
+        let intersection = SmoothIntersection::new(a.clone(), b.clone(), k);
+        assert!((intersection.distance(&p) - (d + k / 4.0)).abs() < 1e-12);
+        // 差集在 d1 = -d2 处同样取 h = 1/2
+        let inner: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0));
+        let q = Vec3::new(0.25, 0.0, 0.0);
+        let subtraction = SmoothSubtraction::new(a.clone(), inner.clone(), k);
+        let (d1, d2) = (a.distance(&q), inner.distance(&q));
+        assert!((d1 + d2).abs() < 1e-12);
+        assert!((subtraction.distance(&q) - (d1 + k / 4.0)).abs() < 1e-12);
+        // 两个距离相差超过 k 时与硬运算一致
+        let far = Vec3::new(-1.0, 0.0, 0.0);
+        let (d1, d2) = (a.distance(&far), b.distance(&far));
+        assert_eq!(union.distance(&far), d1.min(d2));
+        assert_eq!(intersection.distance(&far), d1.max(d2));
+        let subtraction = SmoothSubtraction::new(b.clone(), a.clone(), k);
+        assert_eq!(subtraction.distance(&far), d2.max(-d1));
+        // k = 0 退化为硬运算
+        for p in [p, q, far] {
+            let (d1, d2) = (a.distance(&p), b.distance(&p));
+            assert_eq!(
+                SmoothUnion::new(a.clone(), b.clone(), 0.0).distance(&p),
+                d1.min(d2)
+            );
+            assert_eq!(
+                SmoothIntersection::new(a.clone(), b.clone(), 0.0).distance(&p),
+                d1.max(d2)
+            );
+            assert_eq!(
+                SmoothSubtraction::new(a.clone(), b.clone(), 0.0).distance(&p),
+                d1.max(-d2)
+            );
+        }
+    }
+}
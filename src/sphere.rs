@@ -4,7 +4,8 @@ use crate::interval::Interval;
 use crate::material::Material;
 use crate::onb::Onb;
 use crate::ray::Ray;
-use crate::utility::{INFINITY, PI, random_double};
+use crate::sampler::Sampler;
+use crate::utility::{INFINITY, PI};
 use crate::vec3::Vec3;
 use std::sync::Arc;
 
@@ -42,9 +43,15 @@ impl Sphere {
         let phi = (-p.z).atan2(p.x) + PI;
         (phi / (2.0 * PI), theta / PI)
     }
-    fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
-        let r1 = random_double();
-        let r2 = random_double();
+    fn get_sphere_tangents(p: &Vec3, radius: f64) -> (Vec3, Vec3) {
+        let sin_theta = (p.x * p.x + p.z * p.z).sqrt().max(1e-12);
+        let dpdu = Vec3::new(p.z, 0.0, -p.x) * (2.0 * PI * radius);
+        let dpdv =
+            Vec3::new(-p.x * p.y / sin_theta, sin_theta, -p.y * p.z / sin_theta) * (PI * radius);
+        (dpdu, dpdv)
+    }
+    fn random_to_sphere(radius: f64, distance_squared: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
         let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
         let phi = 2.0 * PI * r1;
         let x = phi.cos() * (1.0 - z * z).sqrt();
@@ -53,8 +60,8 @@ impl Sphere {
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+impl Sphere {
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Vec3)> {
         let current_center = self.center.at(r.tm);
         let oc = current_center - r.origin;
         let a = Vec3::dot(&r.direction, &r.direction);
@@ -62,36 +69,57 @@ impl Hittable for Sphere {
         let c = Vec3::dot(&oc, &oc) - self.radius * self.radius;
         let discriminant = h * h - a * c;
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
         let sqrt_d = discriminant.sqrt();
         let mut root = (h - sqrt_d) / a;
         if !ray_t.surrounds(root) {
             root = (h + sqrt_d) / a;
             if !ray_t.surrounds(root) {
-                return false;
+                return None;
             }
         }
-        rec.t = root;
+        Some((root, (r.at(root) - current_center) / self.radius))
+    }
+}
+impl Hittable for Sphere {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, outward_normal)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
         rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - current_center) / self.radius;
         (rec.u, rec.v) = Self::get_sphere_uv(&outward_normal);
-        rec.mat = self.mat.clone();
-        rec.set_face_normal(r, outward_normal, rec.u, rec.v);
-        if random_double() > self.mat.get_alpha(rec.u, rec.v) {
+        rec.set_material(&self.mat);
+        let (dpdu, dpdv) = Self::get_sphere_tangents(&outward_normal, self.radius);
+        rec.set_shading_frame(r, outward_normal, outward_normal, dpdu, dpdv);
+        if !rec.mat.alpha_test(rec.u, rec.v, sampler) {
             return false;
         }
         true
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t).is_some_and(|(_, outward_normal)| {
+            let (u, v) = Self::get_sphere_uv(&outward_normal);
+            self.mat.alpha_test(u, v, sampler)
+        })
+    }
     fn bounding_box(&self) -> Aabb {
         self.bbox
     }
-    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
         let mut rec = HitRecord::default();
         if !self.hit(
             &Ray::new(*origin, *direction),
             &Interval::new(0.001, INFINITY),
             &mut rec,
+            sampler,
         ) {
             return 0.0;
         }
@@ -100,10 +128,14 @@ impl Hittable for Sphere {
         let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
         1.0 / solid_angle
     }
-    fn random(&self, origin: &Vec3) -> Vec3 {
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
         let direction = self.center.at(0.0) - *origin;
         let distance_squared = direction.squared_length();
         let uvw = Onb::new(&direction);
-        uvw.transform(&Self::random_to_sphere(self.radius, distance_squared))
+        uvw.transform(&Self::random_to_sphere(
+            self.radius,
+            distance_squared,
+            sampler,
+        ))
     }
 }
@@ -0,0 +1,114 @@
+use crate::asset::{LoadError, SearchPaths};
+use crate::hittable_list::HittableList;
+use crate::material::Material;
+use crate::triangle::TriangleMesh;
+use crate::vec3::Vec3;
+use std::fs::File;
+use std::sync::Arc;
+
+pub fn load_stl(
+    stl_filename: &str,
+    scale: f64,
+    mat: Arc<dyn Material>,
+) -> Result<HittableList, LoadError> {
+    let stl_path = SearchPaths::default()
+        .resolve(stl_filename)
+        .ok_or_else(|| LoadError::MissingFile(stl_filename.to_string()))?;
+    let stl = File::open(&stl_path)
+        .and_then(|mut file| stl_io::read_stl(&mut file))
+        .map_err(|err| LoadError::Parse {
+            path: stl_path.clone(),
+            message: err.to_string(),
+        })?;
+    println!("Loading {} facets...", stl.faces.len());
+    let mut triangle_mesh = TriangleMesh::new();
+    for v in &stl.vertices {
+        triangle_mesh
+            .positions
+            .push(Vec3::new(v[0] as f64, v[1] as f64, v[2] as f64) * scale);
+    }
+    for face in &stl.faces {
+        triangle_mesh.indices.push(face.vertices.map(|i| i as u32));
+        triangle_mesh.face_materials.push(0);
+    }
+    triangle_mesh.materials.push(mat);
+    println!("done model loading!");
+    let mut world = HittableList::new();
+    world.add(triangle_mesh.build_bvh());
+    Ok(world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::Interval;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use crate::utility::INFINITY;
+
+    fn write_binary_stl(name: &str, triangles: &[[[f32; 3]; 3]]) -> String {
+        let mut data = vec![0u8; 80];
+        data.extend((triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            data.extend([0u8; 12]);
+            for vertex in triangle {
+                for x in vertex {
+                    data.extend(x.to_le_bytes());
+                }
+            }
+            data.extend([0u8; 2]);
+        }
+        let path = std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn binary_stl_is_scaled_and_hittable() {
+        let path = write_binary_stl(
+            "quad.stl",
+            &[
+                [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+                [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            ],
+        );
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let world = load_stl(&path, 2.0, mat).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut sampler = IndependentSampler::new(0);
+        let mut rec = HitRecord::default();
+        for (x, y, hit) in [
+            (0.5, 1.5, true),
+            (1.5, 0.5, true),
+            (1.9, 1.9, true),
+            (2.5, 1.0, false),
+        ] {
+            let r = Ray::new(Vec3::new(x, y, 3.0), Vec3::new(0.0, 0.0, -1.0));
+            assert_eq!(
+                world.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler),
+                hit
+            );
+            if hit {
+                assert!((rec.t - 3.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn load_errors_are_reported() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        assert!(matches!(
+            load_stl("does/not/exist.stl", 1.0, mat.clone()),
+            Err(LoadError::MissingFile(_))
+        ));
+        let path =
+            std::env::temp_dir().join(format!("raytracer-{}-truncated.stl", std::process::id()));
+        std::fs::write(&path, [0u8; 40]).unwrap();
+        let result = load_stl(&path.to_string_lossy(), 1.0, mat);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(LoadError::Parse { .. })));
+    }
+}
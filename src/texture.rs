@@ -1,33 +1,17 @@
 use crate::color::Color;
+use crate::hittable::HitRecord;
 use crate::interval::Interval;
 use crate::perlin::Perlin;
 use crate::rtw_stb_image::RtwImage;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 use std::sync::Arc;
 
-pub struct UV {
-    pub u: Vec3,
-    pub v: Vec3,
-}
-impl UV {
-    pub fn new(u: Vec3, v: Vec3) -> Self {
-        Self { u, v }
-    }
-    pub fn get_uv(&self, coef: Vec3) -> (f64, f64) {
-        (self.u.dot(&coef), self.v.dot(&coef))
-    }
-}
-impl Default for UV {
-    fn default() -> Self {
-        Self {
-            u: Vec3::new(0.0, 1.0, 0.0),
-            v: Vec3::new(0.0, 0.0, 1.0),
-        }
-    }
-}
-
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Vec3) -> Color;
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.p)
+    }
 }
 pub struct SolidColor {
     pub albedo: Color,
@@ -47,6 +31,21 @@ impl Texture for SolidColor {
         self.albedo
     }
 }
+#[derive(Default)]
+pub struct VertexColor {}
+impl VertexColor {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+impl Texture for VertexColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Vec3) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        rec.color
+    }
+}
 pub struct CheckerTexture {
     inv_scale: f64,
     even: Arc<dyn Texture>,
@@ -83,13 +82,15 @@ impl Texture for CheckerTexture {
 }
 
 pub struct ImageTexture {
-    image: RtwImage,
+    image: Arc<RtwImage>,
+    tint: Color,
 }
 impl ImageTexture {
     pub fn new(filename: &str) -> Self {
-        Self {
-            image: RtwImage::new(filename),
-        }
+        Self::from_image(Arc::new(RtwImage::new(filename)), Color::new(1.0, 1.0, 1.0))
+    }
+    pub fn from_image(image: Arc<RtwImage>, tint: Color) -> Self {
+        Self { image, tint }
     }
 }
 impl Texture for ImageTexture {
@@ -107,7 +108,32 @@ impl Texture for ImageTexture {
             (color_scale * pixel[0] as f64) * (color_scale * pixel[0] as f64),
             (color_scale * pixel[1] as f64) * (color_scale * pixel[1] as f64),
             (color_scale * pixel[2] as f64) * (color_scale * pixel[2] as f64),
-        )
+        ) * self.tint
+    }
+}
+
+pub struct ChannelTexture {
+    image: Arc<RtwImage>,
+    channel: usize,
+    scale: f64,
+}
+impl ChannelTexture {
+    pub fn new(image: Arc<RtwImage>, channel: usize, scale: f64) -> Self {
+        Self {
+            image,
+            channel,
+            scale,
+        }
+    }
+}
+impl Texture for ChannelTexture {
+    fn value(&self, mut u: f64, mut v: f64, _p: &Vec3) -> Color {
+        u = Interval::new(0.0, 1.0).clamp(u);
+        v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
+        let i = (u * self.image.width() as f64) as usize;
+        let j = (v * self.image.height() as f64) as usize;
+        let value = self.image.pixel_data(i, j)[self.channel] as f64 / 255.0 * self.scale;
+        Color::new(value, value, value)
     }
 }
 
@@ -116,9 +142,9 @@ pub struct NoiseTexture {
     scale: f64,
 }
 impl NoiseTexture {
-    pub fn new(scale: f64) -> Self {
+    pub fn new(scale: f64, sampler: &mut dyn Sampler) -> Self {
         Self {
-            noise: Perlin::new(),
+            noise: Perlin::new(sampler),
             scale,
         }
     }
@@ -0,0 +1,278 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utility::PI;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+const EPSILON: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPSILON
+}
+
+fn solve_normalized_quadratic(p: f64, q: f64, roots: &mut Vec<f64>) {
+    let p_half = p / 2.0;
+    let discriminant = p_half * p_half - q;
+    if is_zero(discriminant) {
+        roots.push(-p_half);
+    } else if discriminant > 0.0 {
+        let sqrt_d = discriminant.sqrt();
+        roots.push(sqrt_d - p_half);
+        roots.push(-sqrt_d - p_half);
+    }
+}
+
+fn solve_normalized_cubic(a: f64, b: f64, c: f64, roots: &mut Vec<f64>) {
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+    let start = roots.len();
+    if is_zero(discriminant) {
+        if is_zero(q) {
+            roots.push(0.0);
+        } else {
+            let u = (-q).cbrt();
+            roots.push(2.0 * u);
+            roots.push(-u);
+        }
+    } else if discriminant < 0.0 {
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        roots.push(t * phi.cos());
+        roots.push(-t * (phi + PI / 3.0).cos());
+        roots.push(-t * (phi - PI / 3.0).cos());
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        roots.push((sqrt_d - q).cbrt() - (sqrt_d + q).cbrt());
+    }
+    for root in roots[start..].iter_mut() {
+        *root -= a / 3.0;
+    }
+}
+
+fn solve_quartic(coef: [f64; 5]) -> Vec<f64> {
+    let a = coef[3] / coef[4];
+    let b = coef[2] / coef[4];
+    let c = coef[1] / coef[4];
+    let d = coef[0] / coef[4];
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+    let mut roots = Vec::with_capacity(4);
+    if is_zero(r) {
+        roots.push(0.0);
+        solve_normalized_cubic(0.0, p, q, &mut roots);
+    } else {
+        let mut cubic_roots = Vec::with_capacity(3);
+        solve_normalized_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0, &mut cubic_roots);
+        let z = cubic_roots[0];
+        let mut u = z * z - r;
+        let mut v = 2.0 * z - p;
+        if is_zero(u) {
+            u = 0.0;
+        } else if u > 0.0 {
+            u = u.sqrt();
+        } else {
+            return roots;
+        }
+        if is_zero(v) {
+            v = 0.0;
+        } else if v > 0.0 {
+            v = v.sqrt();
+        } else {
+            return roots;
+        }
+        let v = if q < 0.0 { -v } else { v };
+        solve_normalized_quadratic(v, z - u, &mut roots);
+        solve_normalized_quadratic(-v, z + u, &mut roots);
+    }
+    let eval = |x: f64| (((coef[4] * x + coef[3]) * x + coef[2]) * x + coef[1]) * x + coef[0];
+    let deriv = |x: f64| ((4.0 * coef[4] * x + 3.0 * coef[3]) * x + 2.0 * coef[2]) * x + coef[1];
+    for root in roots.iter_mut() {
+        *root -= a / 4.0;
+        for _ in 0..2 {
+            let slope = deriv(*root);
+            if slope.abs() > EPSILON {
+                *root -= eval(*root) / slope;
+            }
+        }
+    }
+    roots
+}
+
+pub struct Torus {
+    center: Vec3,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+impl Torus {
+    pub fn new(center: Vec3, major_radius: f64, minor_radius: f64, mat: Arc<dyn Material>) -> Self {
+        let extent = Vec3::new(
+            major_radius + minor_radius,
+            minor_radius,
+            major_radius + minor_radius,
+        );
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            mat,
+            bbox: Aabb::new_points(&(center - extent), &(center + extent)),
+        }
+    }
+}
+impl Torus {
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<f64> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+        let scale = self.major_radius;
+        let dir_length = r.direction.length();
+        let o = (r.origin - self.center) / scale;
+        let d = r.direction / dir_length;
+        let minor = self.minor_radius / scale;
+        let f = o.dot(&d);
+        let e = o.dot(&o) - 1.0 - minor * minor;
+        let coef = [
+            e * e - 4.0 * (minor * minor - o.y * o.y),
+            4.0 * f * e + 8.0 * o.y * d.y,
+            4.0 * f * f + 2.0 * e + 4.0 * d.y * d.y,
+            4.0 * f,
+            1.0,
+        ];
+        let to_ray_t = scale / dir_length;
+        solve_quartic(coef)
+            .into_iter()
+            .map(|root| root * to_ray_t)
+            .filter(|t| ray_t.surrounds(*t))
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+    }
+    fn surface(&self, p: &Vec3) -> (Vec3, f64, f64) {
+        let local = *p - self.center;
+        let ring_dir = Vec3::new(local.x, 0.0, local.z).unit();
+        let tube = local - ring_dir * self.major_radius;
+        let phi = (-local.z).atan2(local.x) + PI;
+        let theta = tube.y.atan2(tube.dot(&ring_dir)) + PI;
+        (
+            tube / self.minor_radius,
+            phi / (2.0 * PI),
+            theta / (2.0 * PI),
+        )
+    }
+}
+impl Hittable for Torus {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some(t) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        let (outward_normal, u, v) = self.surface(&rec.p);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&self.mat);
+        rec.set_face_normal(r, outward_normal, rec.u, rec.v);
+        if !rec.mat.alpha_test(rec.u, rec.v, sampler) {
+            return false;
+        }
+        true
+    }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        self.intersect(r, ray_t).is_some_and(|t| {
+            let (_, u, v) = self.surface(&r.at(t));
+            self.mat.alpha_test(u, v, sampler)
+        })
+    }
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::sampler::IndependentSampler;
+    use crate::utility::INFINITY;
+
+    // 由根展开首一多项式, 系数按升幂排列
+    fn from_roots(roots: &[f64]) -> [f64; 5] {
+        let mut coef = [0.0; 5];
+        coef[0] = 1.0;
+        for (k, &root) in roots.iter().enumerate() {
+            for i in (0..=k + 1).rev() {
+                let lower = if i > 0 { coef[i - 1] } else { 0.0 };
+                coef[i] = lower - root * coef[i];
+            }
+        }
+        coef
+    }
+
+    fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        roots
+    }
+
+    #[test]
+    fn quartic_finds_distinct_real_roots() {
+        for expected in [
+            [1.0, 2.0, 3.0, 4.0],
+            [-3.5, -0.25, 0.5, 10.0],
+            [-2.0, -1.0, 1.0, 2.0],
+        ] {
+            let roots = sorted(solve_quartic(from_roots(&expected)));
+            assert_eq!(roots.len(), 4, "{expected:?}: {roots:?}");
+            for (root, want) in roots.iter().zip(expected) {
+                assert!((root - want).abs() < 1e-6, "{expected:?}: {roots:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn quartic_skips_complex_pairs() {
+        // (x - 1)(x + 2)(x^2 + 1)
+        let coef = [-2.0, 1.0, -1.0, 1.0, 1.0];
+        let roots = sorted(solve_quartic(coef));
+        assert_eq!(roots.len(), 2, "{roots:?}");
+        assert!((roots[0] + 2.0).abs() < 1e-6 && (roots[1] - 1.0).abs() < 1e-6);
+        // x^4 + 1 无实根
+        assert!(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn torus_hits_at_the_expected_distances() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let torus = Torus::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 0.25, mat);
+        let mut sampler = IndependentSampler::new(0);
+        let mut rec = HitRecord::default();
+        // 沿 x 轴穿过中心孔: 依次在 x = -1.25, -0.75, 0.75, 1.25 相交
+        let r = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut t_min = 0.001;
+        for expected in [3.75, 4.25, 5.75, 6.25] {
+            assert!(torus.hit(&r, &Interval::new(t_min, INFINITY), &mut rec, &mut sampler));
+            assert!((rec.t - expected).abs() < 1e-6, "{} vs {}", rec.t, expected);
+            assert!((rec.normal.length() - 1.0).abs() < 1e-9);
+            t_min = rec.t + 1e-4;
+        }
+        assert!(!torus.hit(&r, &Interval::new(t_min, INFINITY), &mut rec, &mut sampler));
+        // 沿对称轴穿过中心孔, 不相交
+        let r = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(!torus.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+    }
+}
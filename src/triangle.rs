@@ -1,127 +1,460 @@
 use crate::aabb::Aabb;
+use crate::bvh::{Bvh, BvhBuilder};
+use crate::color::Color;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::texture::UV;
-use crate::utility::{INFINITY, random_double};
+use crate::sampler::Sampler;
+use crate::utility::{INFINITY, degrees_to_radians};
 use crate::vec3::Vec3;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
+
+const SMOOTHING_ANGLE: f64 = 60.0;
+const LBVH_THRESHOLD: usize = 1_000_000;
+
+#[derive(Default)]
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3>,
+    pub texcoords: Vec<[f64; 2]>,
+    pub colors: Vec<Color>,
+    pub normals: Vec<Vec3>,
+    pub normal_indices: Vec<[u32; 3]>,
+    pub tangents: Vec<Vec3>,
+    pub tangent_signs: Vec<f64>,
+    pub indices: Vec<[u32; 3]>,
+    pub materials: Vec<Arc<dyn Material>>,
+    pub face_materials: Vec<u32>,
+}
+impl TriangleMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn face_count(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn build_bvh(mut self) -> Arc<dyn Hittable> {
+        if self.normals.is_empty() {
+            self.compute_normals(SMOOTHING_ANGLE);
+        } else if self.normal_indices.is_empty() {
+            self.normal_indices = self.indices.clone();
+        }
+        self.compute_tangents();
+        let mesh = Arc::new(self);
+        let triangles = (0..mesh.face_count())
+            .map(|face| Triangle::new(&mesh, face))
+            .collect();
+        let builder = if mesh.face_count() > LBVH_THRESHOLD {
+            BvhBuilder::Lbvh
+        } else {
+            BvhBuilder::Sah
+        };
+        let start = Instant::now();
+        let bvh: Bvh<Triangle> = Bvh::with_builder(triangles, builder);
+        println!("{}, built in {:.2?}", bvh.stats(), start.elapsed());
+        Arc::new(bvh)
+    }
+    pub fn compute_normals(&mut self, max_angle: f64) {
+        let cos_max = degrees_to_radians(max_angle).cos();
+        let face_normals: Vec<Vec3> = (0..self.face_count())
+            .map(|face| {
+                let (p0, p1, p2) = self.vertices(face);
+                (p1 - p0).cross(&(p2 - p0))
+            })
+            .collect();
+        let key = |p: &Vec3| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut incident: HashMap<[u64; 3], Vec<usize>> = HashMap::new();
+        for (face, corners) in self.indices.iter().enumerate() {
+            for &i in corners {
+                incident
+                    .entry(key(&self.positions[i as usize]))
+                    .or_default()
+                    .push(face);
+            }
+        }
+        self.normals.clear();
+        self.normal_indices.clear();
+        // 同一位置上落在同一平滑组内的角共享法线
+        let mut shared: HashMap<([u64; 3], [u64; 3]), u32> = HashMap::new();
+        for (face, corners) in self.indices.iter().enumerate() {
+            let face_normal = if face_normals[face].length() == 0.0 {
+                Vec3::new(0.0, 1.0, 0.0)
+            } else {
+                face_normals[face].unit()
+            };
+            let mut normal_index = [0; 3];
+            for (k, &i) in corners.iter().enumerate() {
+                let mut sum = Vec3::default();
+                for &other in &incident[&key(&self.positions[i as usize])] {
+                    let n = face_normals[other];
+                    if n.length() > 0.0 && n.unit().dot(&face_normal) >= cos_max {
+                        sum = sum + n;
+                    }
+                }
+                let normal = if sum.length() == 0.0 {
+                    face_normal
+                } else {
+                    sum.unit()
+                };
+                let normals = &mut self.normals;
+                normal_index[k] = *shared
+                    .entry((key(&self.positions[i as usize]), key(&normal)))
+                    .or_insert_with(|| {
+                        normals.push(normal);
+                        normals.len() as u32 - 1
+                    });
+            }
+            self.normal_indices.push(normal_index);
+        }
+    }
+    pub fn compute_tangents(&mut self) {
+        if self.texcoords.is_empty() || self.normals.is_empty() {
+            return;
+        }
+        let mut tangents = vec![Vec3::default(); self.normals.len()];
+        let mut bitangents = vec![Vec3::default(); self.normals.len()];
+        for face in 0..self.face_count() {
+            if let Some((dpdu, dpdv)) = self.face_tangents(face) {
+                for &i in &self.normal_indices[face] {
+                    tangents[i as usize] = tangents[i as usize] + dpdu.unit();
+                    bitangents[i as usize] = bitangents[i as usize] + dpdv.unit();
+                }
+            }
+        }
+        // 对顶点法线做 Gram-Schmidt 正交化, 并只保留副切线的手性
+        self.tangent_signs = vec![1.0; self.normals.len()];
+        for (i, normal) in self.normals.iter().enumerate() {
+            let tangent = tangents[i] - *normal * normal.dot(&tangents[i]);
+            if tangent.near_zero() {
+                tangents[i] = Vec3::default();
+                continue;
+            }
+            tangents[i] = tangent.unit();
+            if normal.cross(&tangents[i]).dot(&bitangents[i]) < 0.0 {
+                self.tangent_signs[i] = -1.0;
+            }
+        }
+        self.tangents = tangents;
+    }
+    fn vertices(&self, face: usize) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.indices[face];
+        (
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        )
+    }
+    // alpha, beta 是沿 p1 - p0, p2 - p0 的重心坐标, 顶点 0, 1, 2 的权重为 (gamma, alpha, beta);
+    // 无纹理坐标时直接使用 (alpha, beta), 与 Quad 的边参数化一致
+    fn texcoord(&self, face: usize, alpha: f64, beta: f64) -> (f64, f64) {
+        if self.texcoords.is_empty() {
+            return (alpha, beta);
+        }
+        let [i0, i1, i2] = self.indices[face];
+        let t0 = self.texcoords[i0 as usize];
+        let t1 = self.texcoords[i1 as usize];
+        let t2 = self.texcoords[i2 as usize];
+        let gamma = 1.0 - alpha - beta;
+        (
+            gamma * t0[0] + alpha * t1[0] + beta * t2[0],
+            gamma * t0[1] + alpha * t1[1] + beta * t2[1],
+        )
+    }
+    fn shading_normal(&self, face: usize, alpha: f64, beta: f64) -> Option<Vec3> {
+        if self.normals.is_empty() {
+            return None;
+        }
+        let [i0, i1, i2] = self.normal_indices[face];
+        let gamma = 1.0 - alpha - beta;
+        let n = self.normals[i0 as usize] * gamma
+            + self.normals[i1 as usize] * alpha
+            + self.normals[i2 as usize] * beta;
+        if n.length() == 0.0 {
+            None
+        } else {
+            Some(n.unit())
+        }
+    }
+    fn face_tangents(&self, face: usize) -> Option<(Vec3, Vec3)> {
+        let (p0, p1, p2) = self.vertices(face);
+        let (u0, v0) = self.texcoord(face, 0.0, 0.0);
+        let (u1, v1) = self.texcoord(face, 1.0, 0.0);
+        let (u2, v2) = self.texcoord(face, 0.0, 1.0);
+        let (du1, dv1) = (u1 - u0, v1 - v0);
+        let (du2, dv2) = (u2 - u0, v2 - v0);
+        let det = du1 * dv2 - dv1 * du2;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        Some((
+            (edge1 * dv2 - edge2 * dv1) / det,
+            (edge2 * du1 - edge1 * du2) / det,
+        ))
+    }
+    fn tangents(&self, face: usize, alpha: f64, beta: f64) -> Option<(Vec3, Vec3)> {
+        let (dpdu, dpdv) = self.face_tangents(face)?;
+        if self.tangents.is_empty() {
+            return Some((dpdu, dpdv));
+        }
+        let Some(normal) = self.shading_normal(face, alpha, beta) else {
+            return Some((dpdu, dpdv));
+        };
+        let [i0, i1, i2] = self.normal_indices[face];
+        let gamma = 1.0 - alpha - beta;
+        let tangent = self.tangents[i0 as usize] * gamma
+            + self.tangents[i1 as usize] * alpha
+            + self.tangents[i2 as usize] * beta;
+        let tangent = tangent - normal * normal.dot(&tangent);
+        if tangent.near_zero() {
+            return Some((dpdu, dpdv));
+        }
+        let tangent = tangent.unit();
+        let sign = (self.tangent_signs[i0 as usize] * gamma
+            + self.tangent_signs[i1 as usize] * alpha
+            + self.tangent_signs[i2 as usize] * beta)
+            .signum();
+        Some((tangent, normal.cross(&tangent) * sign))
+    }
+    fn color(&self, face: usize, alpha: f64, beta: f64) -> Color {
+        if self.colors.is_empty() {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let [i0, i1, i2] = self.indices[face];
+        let gamma = 1.0 - alpha - beta;
+        self.colors[i0 as usize] * gamma
+            + self.colors[i1 as usize] * alpha
+            + self.colors[i2 as usize] * beta
+    }
+    fn material(&self, face: usize) -> &Arc<dyn Material> {
+        &self.materials[self.face_materials[face] as usize]
+    }
+}
 
 pub struct Triangle {
-    q: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    mat: Arc<dyn Material>,
-    bbox: Aabb,
-    normal: Vec3,
-    d: f64,
-    area: f64,
-    uv: UV,
+    mesh: Arc<TriangleMesh>,
+    face: u32,
 }
 impl Triangle {
-    // pub fn new_default(q: &Vec3, u: &Vec3, v: &Vec3, mat: Arc<dyn Material>) -> Self {
-    //     let bbox_diagonal1 = Aabb::new_points(q, &(*q + *u));
-    //     let bbox_diagonal2 = Aabb::new_points(&(*q + *u), &(*q + *v));
-    //     let n = u.cross(v);
-    //     let normal = n.unit();
-    //     Self {
-    //         q: *q,
-    //         u: *u,
-    //         v: *v,
-    //         w: n / n.dot(&n),
-    //         mat: mat.clone(),
-    //         bbox: Aabb::new_aabb(&bbox_diagonal1, &bbox_diagonal2),
-    //         normal,
-    //         d: normal.dot(q),
-    //         area: n.length() / 2.0,
-    //         uv: UV::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
-    //     }
-    // }
-    pub fn new(q: &Vec3, u: &Vec3, v: &Vec3, mat: Arc<dyn Material>, uv: UV) -> Self {
-        let bbox_diagonal1 = Aabb::new_points(q, &(*q + *u));
-        let bbox_diagonal2 = Aabb::new_points(&(*q + *u), &(*q + *v));
-        let n = u.cross(v);
-        let normal = n.unit();
+    pub fn new(mesh: &Arc<TriangleMesh>, face: usize) -> Self {
         Self {
-            q: *q,
-            u: *u,
-            v: *v,
-            w: n / n.dot(&n),
-            mat: mat.clone(),
-            bbox: Aabb::new_aabb(&bbox_diagonal1, &bbox_diagonal2),
-            normal,
-            d: normal.dot(q),
-            area: n.length() / 2.0,
-            uv,
-        }
-    }
-    fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
-        let unit_interval = Interval::new(0.0, 1.0);
-        if !unit_interval.contains(a)
-            || !unit_interval.contains(b)
-            || !unit_interval.contains(a + b)
-        {
-            return false;
+            mesh: mesh.clone(),
+            face: face as u32,
         }
-        rec.u = a;
-        rec.v = b;
-        true
     }
-}
-impl Hittable for Triangle {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        let denom = self.normal.dot(&r.direction);
-        if denom.abs() < 1e-8 {
-            return false;
+    fn area(&self) -> f64 {
+        let (p0, p1, p2) = self.mesh.vertices(self.face as usize);
+        (p1 - p0).cross(&(p2 - p0)).length() / 2.0
+    }
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64, f64)> {
+        let (p0, p1, p2) = self.mesh.vertices(self.face as usize);
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let pvec = r.direction.cross(&edge2);
+        let det = edge1.dot(&pvec);
+        if det.abs() < 1e-12 {
+            return None;
         }
-        let t = (self.d - self.normal.dot(&r.origin)) / denom;
+        let inv_det = 1.0 / det;
+        let tvec = r.origin - p0;
+        let alpha = tvec.dot(&pvec) * inv_det;
+        if !(0.0..=1.0).contains(&alpha) {
+            return None;
+        }
+        let qvec = tvec.cross(&edge1);
+        let beta = r.direction.dot(&qvec) * inv_det;
+        if beta < 0.0 || alpha + beta > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(&qvec) * inv_det;
         if !ray_t.contains(t) {
-            return false;
+            return None;
         }
-        let intersection = r.at(t);
-        let planar_hitpt_vector = intersection - self.q;
-        let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
-        let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
-        if !Self::is_interior(alpha, beta, rec) {
+        Some((t, alpha, beta))
+    }
+}
+impl Hittable for Triangle {
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: &Interval,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let Some((t, alpha, beta)) = self.intersect(r, ray_t) else {
             return false;
-        }
+        };
+        let face = self.face as usize;
+        let (p0, p1, p2) = self.mesh.vertices(face);
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
         rec.t = t;
-        rec.p = intersection;
-        rec.mat = self.mat.clone();
-        let coef = Vec3::new(alpha, beta, 1.0 - alpha - beta);
-        (rec.u, rec.v) = self.uv.get_uv(coef);
-        rec.set_face_normal(r, self.normal, alpha, beta);
-        if random_double() > self.mat.get_alpha(alpha, beta) {
+        rec.p = r.at(t);
+        rec.set_material(self.mesh.material(face));
+        (rec.u, rec.v) = self.mesh.texcoord(face, alpha, beta);
+        rec.color = self.mesh.color(face, alpha, beta);
+        let outward_normal = edge1.cross(&edge2).unit();
+        let shading_normal = self
+            .mesh
+            .shading_normal(face, alpha, beta)
+            .unwrap_or(outward_normal);
+        let (dpdu, dpdv) = self
+            .mesh
+            .tangents(face, alpha, beta)
+            .unwrap_or((edge1, edge2));
+        rec.set_shading_frame(r, outward_normal, shading_normal, dpdu, dpdv);
+        if !rec.mat.alpha_test(rec.u, rec.v, sampler) {
             return false;
         }
         true
     }
+    fn occluded(&self, r: &Ray, ray_t: &Interval, sampler: &mut dyn Sampler) -> bool {
+        let face = self.face as usize;
+        self.intersect(r, ray_t).is_some_and(|(_, alpha, beta)| {
+            let (u, v) = self.mesh.texcoord(face, alpha, beta);
+            self.mesh.material(face).alpha_test(u, v, sampler)
+        })
+    }
     fn bounding_box(&self) -> Aabb {
-        self.bbox
+        let (p0, p1, p2) = self.mesh.vertices(self.face as usize);
+        Aabb::new_aabb(&Aabb::new_points(&p0, &p1), &Aabb::new_points(&p0, &p2))
     }
-    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3, sampler: &mut dyn Sampler) -> f64 {
         let mut rec = HitRecord::default();
         if !self.hit(
             &Ray::new(*origin, *direction),
             &Interval::new(0.001, INFINITY),
             &mut rec,
+            sampler,
         ) {
             return 0.0;
         }
         let distance_squared = rec.t * rec.t * direction.squared_length();
-        let cosine = (direction.dot(&rec.normal) / direction.length()).abs();
-        distance_squared / (cosine * self.area)
+        let cosine = (direction.dot(&rec.geometric_normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area())
     }
-    fn random(&self, origin: &Vec3) -> Vec3 {
-        let mut a = random_double();
-        let mut b = random_double();
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (p0, p1, p2) = self.mesh.vertices(self.face as usize);
+        let (mut a, mut b) = sampler.get_2d();
         if a + b > 1.0 {
             a = 1.0 - a;
             b = 1.0 - b;
         }
-        let p = self.q + (self.u * a) + (self.v * b);
+        let p = p0 + ((p1 - p0) * a) + ((p2 - p0) * b);
         p - *origin
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sampler::IndependentSampler;
+
+    fn unit_triangle(texcoords: Vec<[f64; 2]>) -> Triangle {
+        let mesh = TriangleMesh {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            texcoords,
+            indices: vec![[0, 1, 2]],
+            materials: vec![Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))],
+            face_materials: vec![0],
+            ..TriangleMesh::default()
+        };
+        Triangle::new(&Arc::new(mesh), 0)
+    }
+
+    fn hit_uv(triangle: &Triangle, alpha: f64, beta: f64) -> (f64, f64) {
+        let target = Vec3::new(alpha, beta, 0.0);
+        let r = Ray::new(target + Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        let mut sampler = IndependentSampler::new(0);
+        assert!(triangle.hit(&r, &Interval::new(0.001, INFINITY), &mut rec, &mut sampler));
+        (rec.u, rec.v)
+    }
+
+    #[test]
+    fn texcoords_interpolate_with_vertex_weights() {
+        let t = [[0.1, 0.2], [0.9, 0.3], [0.4, 0.8]];
+        let triangle = unit_triangle(t.to_vec());
+        for (alpha, beta) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.25, 0.5), (0.6, 0.1)] {
+            let gamma = 1.0 - alpha - beta;
+            let expected = (
+                gamma * t[0][0] + alpha * t[1][0] + beta * t[2][0],
+                gamma * t[0][1] + alpha * t[1][1] + beta * t[2][1],
+            );
+            let (u, v) = hit_uv(&triangle, alpha, beta);
+            assert!((u - expected.0).abs() < 1e-9 && (v - expected.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn smoothing_groups_share_generated_normals() {
+        let mut mesh = TriangleMesh {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(0.0, 1.0, 1.0),
+            ],
+            // 两个共面三角形组成的正方形, 加上一个与之成 90 度的三角形
+            indices: vec![[0, 1, 2], [0, 2, 3], [0, 3, 5]],
+            ..TriangleMesh::default()
+        };
+        mesh.compute_normals(SMOOTHING_ANGLE);
+        assert_eq!(mesh.normal_indices[0][0], mesh.normal_indices[1][0]);
+        assert_eq!(mesh.normal_indices[0][2], mesh.normal_indices[1][1]);
+        assert_ne!(mesh.normal_indices[1][0], mesh.normal_indices[2][0]);
+        assert_eq!(mesh.normals.len(), 4 + 3);
+    }
+
+    #[test]
+    fn tangent_frames_are_orthonormal_and_continuous() {
+        let mut mesh = TriangleMesh {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.3),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            texcoords: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            indices: vec![[0, 1, 2], [0, 2, 3]],
+            ..TriangleMesh::default()
+        };
+        mesh.compute_normals(SMOOTHING_ANGLE);
+        mesh.compute_tangents();
+        // 共享顶点 0 和 2 在两个面上得到相同的切线框架
+        for ((face_a, alpha_a, beta_a), (face_b, alpha_b, beta_b)) in [
+            ((0, 0.0, 0.0), (1, 0.0, 0.0)),
+            ((0, 0.0, 1.0), (1, 1.0, 0.0)),
+        ] {
+            let (ta, ba) = mesh.tangents(face_a, alpha_a, beta_a).unwrap();
+            let (tb, bb) = mesh.tangents(face_b, alpha_b, beta_b).unwrap();
+            assert!((ta - tb).length() < 1e-9 && (ba - bb).length() < 1e-9);
+        }
+        for face in 0..mesh.face_count() {
+            let (tangent, bitangent) = mesh.tangents(face, 0.3, 0.3).unwrap();
+            let normal = mesh.shading_normal(face, 0.3, 0.3).unwrap();
+            let (_, dpdv) = mesh.face_tangents(face).unwrap();
+            assert!(tangent.dot(&normal).abs() < 1e-9);
+            assert!((tangent.length() - 1.0).abs() < 1e-9);
+            assert!((bitangent.length() - 1.0).abs() < 1e-9);
+            assert!(bitangent.dot(&dpdv) > 0.0);
+        }
+    }
+
+    #[test]
+    fn missing_texcoords_fall_back_to_edge_parameters() {
+        let triangle = unit_triangle(Vec::new());
+        let (u, v) = hit_uv(&triangle, 0.3, 0.45);
+        assert!((u - 0.3).abs() < 1e-9 && (v - 0.45).abs() < 1e-9);
+    }
+}
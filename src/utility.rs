@@ -1,4 +1,4 @@
-use rand::Rng;
+use crate::sampler::Sampler;
 
 pub const INFINITY: f64 = f64::MAX;
 pub const PI: f64 = std::f64::consts::PI;
@@ -7,12 +7,44 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
-pub fn random_double() -> f64 {
-    rand::rng().random_range(0.0..1.0)
+pub fn random_double_range(min: f64, max: f64, sampler: &mut dyn Sampler) -> f64 {
+    min + sampler.get_1d() * (max - min)
 }
-pub fn random_double_range(min: f64, max: f64) -> f64 {
-    min + random_double() * (max - min)
+pub fn random_int_range(min: i32, max: i32, sampler: &mut dyn Sampler) -> i32 {
+    random_double_range(min as f64, max as f64 + 1.0, sampler) as i32
 }
-pub fn random_int_range(min: i32, max: i32) -> i32 {
-    random_double_range(min as f64, max as f64 + 1.0) as i32
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quadratic_roots_are_ordered_and_stable() {
+        let (t0, t1) = solve_quadratic(1.0, -3.0, 2.0).unwrap();
+        assert_eq!((t0, t1), (1.0, 2.0));
+        // b^2 远大于 4ac 时, 小根不应因相消而丢失精度
+        let (t0, t1) = solve_quadratic(1.0, -1e8, 1.0).unwrap();
+        assert!((t0 - 1e-8).abs() < 1e-20);
+        assert!((t1 - 1e8).abs() < 1e-6);
+        assert!(solve_quadratic(1.0, 0.0, 1.0).is_none());
+        // 退化为一次方程
+        assert_eq!(solve_quadratic(0.0, 2.0, -1.0), Some((0.5, 0.5)));
+        assert!(solve_quadratic(0.0, 0.0, 1.0).is_none());
+    }
 }
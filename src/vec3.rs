@@ -1,4 +1,5 @@
-use crate::utility::{PI, random_double, random_double_range};
+use crate::sampler::Sampler;
+use crate::utility::{PI, random_double_range};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
@@ -44,27 +45,25 @@ impl Vec3 {
             self.z / self.length(),
         )
     }
-    pub fn random() -> Vec3 {
-        Vec3::new(random_double(), random_double(), random_double())
+    pub fn random(sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(sampler.get_1d(), sampler.get_1d(), sampler.get_1d())
     }
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
+    pub fn random_range(min: f64, max: f64, sampler: &mut dyn Sampler) -> Vec3 {
         Vec3::new(
-            random_double_range(min, max),
-            random_double_range(min, max),
-            random_double_range(min, max),
+            random_double_range(min, max, sampler),
+            random_double_range(min, max, sampler),
+            random_double_range(min, max, sampler),
         )
     }
-    pub fn random_unit_vector() -> Vec3 {
-        loop {
-            let p = Self::random_range(-1.0, 1.0);
-            let lensq = p.squared_length();
-            if lensq <= 1.0 {
-                return p / lensq.sqrt();
-            }
-        }
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let z = 1.0 - 2.0 * r1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * r2;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
     }
-    pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
-        let on_unit_sphere = Self::random_unit_vector();
+    pub fn random_on_hemisphere(normal: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let on_unit_sphere = Self::random_unit_vector(sampler);
         if Self::dot(&on_unit_sphere, normal) > 0.0 {
             on_unit_sphere
         } else {
@@ -84,17 +83,23 @@ impl Vec3 {
         let r_out_parallel = (*n) * -f64::abs(1.0 - r_out_perp.squared_length()).sqrt();
         r_out_perp + r_out_parallel
     }
-    pub fn random_in_unit_disk() -> Self {
-        loop {
-            let p = Vec3::new(
-                random_double_range(-1.0, 1.0),
-                random_double_range(-1.0, 1.0),
-                0.0,
-            );
-            if p.squared_length() < 1.0 {
-                return p;
-            }
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        Self::concentric_disk(u, v)
+    }
+    // 同心映射 (Shirley-Chiu): 把 [0,1)² 的同心正方形映射到同心圆, 保持面积和分层结构
+    pub fn concentric_disk(u: f64, v: f64) -> Self {
+        let a = 2.0 * u - 1.0;
+        let b = 2.0 * v - 1.0;
+        if a == 0.0 && b == 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
         }
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4.0 * (b / a))
+        } else {
+            (b, PI / 2.0 - PI / 4.0 * (a / b))
+        };
+        Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
     }
     pub fn has_nan(&self) -> bool {
         self.x.is_nan() || self.y.is_nan() || self.z.is_nan()
@@ -143,12 +148,38 @@ impl Div<f64> for Vec3 {
     }
 }
 
-pub fn random_cosine_direction() -> Vec3 {
-    let r1 = random_double();
-    let r2 = random_double();
+pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+    let (r1, r2) = sampler.get_2d();
     let phi = 2.0 * PI * r1;
     let x = phi.cos() * r2.sqrt();
     let y = phi.sin() * r2.sqrt();
     let z = (1.0 - r2).sqrt();
     Vec3::new(x, y, z)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concentric_disk_preserves_area() {
+        let n = 100;
+        let mut inner = 0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = (i as f64 + 0.5) / n as f64;
+                let v = (j as f64 + 0.5) / n as f64;
+                let p = Vec3::concentric_disk(u, v);
+                assert!(p.length() <= 1.0 + 1e-12);
+                if p.length() < 0.5 {
+                    inner += 1;
+                }
+            }
+        }
+        // 半径 0.5 的圆占单位圆面积的 1/4, 对应中心 1/4 的正方形
+        assert_eq!(inner, n * n / 4);
+        assert!((Vec3::concentric_disk(1.0, 0.5).x - 1.0).abs() < 1e-12);
+        assert!((Vec3::concentric_disk(0.5, 1.0).y - 1.0).abs() < 1e-12);
+        assert!((Vec3::concentric_disk(0.0, 0.0).length() - 1.0).abs() < 1e-12);
+    }
+}